@@ -0,0 +1,408 @@
+// File carving: searches the unused blocks of the file system for known file formats.
+// Every format is a Carver, the CarvingEngine feeds them the free blocks from BlockIter.
+
+use std::collections::VecDeque;
+use std::{fs, io};
+use std::io::Write;
+
+use crate::ext2::{BlockIter, Ext2FS};
+
+mod bits;
+mod bmp;
+mod bzip2;
+mod elf;
+mod flac;
+mod gif;
+mod gzip;
+mod jpeg;
+mod mp3;
+mod mp4;
+mod pdf;
+mod png;
+mod riff;
+mod script;
+mod sevenzip;
+mod sqlite;
+mod tar;
+mod text;
+mod xz;
+mod zip;
+
+pub use bmp::BmpCarver;
+pub use bzip2::Bzip2Carver;
+pub use elf::ElfCarver;
+pub use flac::FlacCarver;
+pub use gif::GifCarver;
+pub use gzip::GzipCarver;
+pub use jpeg::JpegCarver;
+pub use mp3::Mp3Carver;
+pub use mp4::Mp4Carver;
+pub use pdf::PdfCarver;
+pub use png::PngCarver;
+pub use riff::RiffCarver;
+pub use script::ScriptCarver;
+pub use sevenzip::SevenZipCarver;
+pub use sqlite::{SqliteCarver, SqlitePageCarver};
+pub use tar::TarCarver;
+pub use text::TextCarver;
+pub use xz::XzCarver;
+pub use zip::ZipCarver;
+
+/// Result of a Carver looking for the end of its file
+pub enum Scan {
+    /// The end is not in the data yet, more blocks are needed
+    NeedMore,
+    /// The file is this many bytes long. For formats with a length field this can be
+    /// more than the data seen so far, the engine then reads up to there.
+    End(usize),
+    /// The file is `end` bytes long, unless the data up to `lookahead` continues it.
+    /// The engine reads up to there and asks again, without more free blocks the file ends at `end`.
+    MaybeEnd { end: usize, lookahead: usize },
+    /// The data is not a file of this format
+    Invalid,
+}
+
+/// A file format, that can be recovered from unused blocks
+pub trait Carver {
+    /// Name of the format for messages, e.g. "JPEG"
+    fn name(&self) -> &'static str;
+
+    /// File extension for recovered files
+    fn extension(&self) -> &'static str;
+
+    /// File extension for a complete file, containers shared by several formats can look at the content
+    fn extension_for(&self, _data: &[u8]) -> &'static str {
+        self.extension()
+    }
+
+    /// Maximum size of a file, we give up on files without an end in sight
+    fn max_size(&self) -> usize;
+
+    /// Header detection: offset of the start of a file in this block
+    fn find_header(&self, block: &[u8]) -> Option<usize>;
+
+    /// Files of this format are only searched at the start of a block, not behind the end of another
+    /// file inside a block. Formats, that check the data block by block, need it to start with a block.
+    fn starts_at_block(&self) -> bool {
+        false
+    }
+
+    /// Footer or length detection: `data` starts at the header and contains all blocks read so far
+    fn find_end(&self, data: &[u8]) -> Scan;
+
+    /// The free blocks ran out while find_end still needed more: the end of the file, if it can be
+    /// complete with the data so far. Formats without an explicit end take what they have accepted.
+    fn find_end_at_eof(&self, _data: &[u8]) -> Option<usize> {
+        None
+    }
+
+    /// Last check of a complete file, to reject false positives
+    fn validate(&self, _data: &[u8]) -> bool {
+        true
+    }
+}
+
+/// Drives any number of carvers over the unused blocks of a file system
+pub struct CarvingEngine {
+    carvers: Vec<Box<dyn Carver>>,
+}
+
+// Most data read at once for a file without a known length, find_end scans it again after every read
+const MAX_READ_AHEAD: usize = 16 * 1024 * 1024;
+
+// The unused blocks of the file system, read from BlockIter when they are needed.
+// Only the blocks from the search position on are kept, a carve reads ahead and can go back.
+struct FreeBlocks<'a> {
+    blocks: BlockIter<'a>,
+    // Number of unused blocks, to know how much can follow
+    count: usize,
+    // Index of the first kept block
+    first: usize,
+    // (Group Number, Block Number) of the kept blocks
+    kept: VecDeque<(usize, usize)>,
+}
+
+impl<'a> FreeBlocks<'a> {
+    fn new(ext2_fs: &'a Ext2FS) -> Self {
+        let count = BlockIter::new(ext2_fs).filter(|&(_, _, is_used)| !is_used).count();
+        FreeBlocks { blocks: BlockIter::new(ext2_fs), count, first: 0, kept: VecDeque::new() }
+    }
+
+    // The unused block with this index, None behind the last one
+    fn get(&mut self, index: usize) -> Option<(usize, usize)> {
+        while self.first + self.kept.len() <= index {
+            let (group_number, block_number, _) = self.blocks.find(|&(_, _, is_used)| !is_used)?;
+            self.kept.push_back((group_number, block_number));
+        }
+        self.kept.get(index.checked_sub(self.first)?).copied()
+    }
+
+    // Number of unused blocks from this index on
+    fn remaining(&self, index: usize) -> usize {
+        self.count.saturating_sub(index)
+    }
+
+    // The blocks in front of index are not needed anymore
+    fn forget_before(&mut self, index: usize) {
+        while self.first < index && self.kept.pop_front().is_some() {
+            self.first += 1;
+        }
+    }
+}
+
+// A file found by a carver
+struct CarvedFile {
+    data: Vec<u8>,
+    // Where to continue: index in the free blocks and offset in that block.
+    // If the file ends inside a block, the rest of the block can hold the next file.
+    next_block: usize,
+    next_offset: usize,
+}
+
+impl CarvingEngine {
+    pub fn new() -> Self {
+        CarvingEngine { carvers: vec![] }
+    }
+
+    /// Adds a format, carvers added first win if several formats match a block
+    pub fn add(&mut self, carver: Box<dyn Carver>) {
+        self.carvers.push(carver);
+    }
+
+    /// Carves all unused blocks and saves the files to `path`, returns the number of recovered files
+    pub fn run(&self, ext2_fs: &Ext2FS, path: &str) -> io::Result<usize> {
+        let mut free_blocks = FreeBlocks::new(ext2_fs);
+
+        let mut recovered = 0;
+        let mut index = 0;
+        // Offset in the block, where the search for headers continues
+        let mut start_offset = 0;
+        while let Some((group_number, block_number)) = free_blocks.get(index) {
+            free_blocks.forget_before(index);
+            let block_data = ext2_fs.read_block(block_number as u32)?;
+
+            let mut next = (index + 1, 0);
+            // First start of every carver: (offset, number of the carver)
+            let mut headers: Vec<(usize, usize)> = self.carvers.iter().enumerate()
+                .filter_map(|(number, carver)| Some((CarvingEngine::find_header(carver.as_ref(), &block_data, start_offset)?, number)))
+                .collect();
+            // A block can hold any number of starts and ends, handle them in order.
+            // For the same offset the carver added first wins.
+            while let Some(&(offset, number)) = headers.iter().min() {
+                headers.retain(|&header| header != (offset, number));
+                let carver = self.carvers[number].as_ref();
+                println!("{} Start found in Block Group {}, Block {}", carver.name(), group_number, block_number);
+
+                let carved = self.carve(ext2_fs, carver, &mut free_blocks, index, block_data[offset..].to_vec())?;
+                if let Some(carved) = carved {
+                    // Save the data to a file, files inside a block also get the offset in the name
+                    let extension = carver.extension_for(&carved.data);
+                    let filename = if offset == 0 {
+                        format!("{}/recovered_{}_{}.{}", path, group_number, block_number, extension)
+                    } else {
+                        format!("{}/recovered_{}_{}_{}.{}", path, group_number, block_number, offset, extension)
+                    };
+                    let mut file = fs::File::create(&filename)?;
+                    file.write_all(&carved.data)?;
+                    println!("{} saved to {}", carver.name(), filename);
+
+                    recovered += 1;
+                    // Continue behind the file
+                    next = (carved.next_block, carved.next_offset);
+                    break;
+                }
+                // Behind the end of another file, the carver can find another start later in the block
+                if start_offset > 0 {
+                    if let Some(found) = CarvingEngine::find_header(carver, &block_data, offset + 1) {
+                        headers.push((found, number));
+                    }
+                }
+            }
+            (index, start_offset) = next;
+        }
+
+        Ok(recovered)
+    }
+
+    // First start of a file of the carver in the block, at or behind from.
+    // At the start of a block every carver decides itself, where its files may start. Behind the end of
+    // another file we try every offset, a small file can follow directly inside the same block.
+    fn find_header(carver: &dyn Carver, block: &[u8], from: usize) -> Option<usize> {
+        if from == 0 {
+            carver.find_header(block)
+        } else if carver.starts_at_block() {
+            None
+        } else {
+            (from..block.len()).find_map(|offset| carver.find_header(&block[offset..]).map(|found| offset + found))
+        }
+    }
+
+    // Collects free blocks starting at free_blocks[index], until the carver knows where the file ends
+    // The file starts inside the block, data holds the rest of the block.
+    fn carve(&self, ext2_fs: &Ext2FS, carver: &dyn Carver, free_blocks: &mut FreeBlocks, index: usize, mut data: Vec<u8>) -> io::Result<Option<CarvedFile>> {
+        let block_size = ext2_fs.super_block.block_size() as usize;
+        // For every block in data: (end of the block in data, index in free_blocks)
+        let mut block_ends = vec![(data.len(), index)];
+        let mut next_index = index + 1;
+        // Data blocks of the file so far, and indirect blocks skipped since the last one
+        let mut data_blocks = 1;
+        let mut skipped = 0;
+
+        let length = loop {
+            // Where the file ends, if no more data comes: MaybeEnd tells it, formats without an end are asked
+            let mut fallback = None;
+            let mut open_end = false;
+            // How much data do we need for the next try
+            let wanted = match carver.find_end(&data) {
+                Scan::End(length) if length <= data.len() => break length,
+                Scan::End(length) | Scan::MaybeEnd { end: length, .. } if length > carver.max_size() => {
+                    println!("{} is bigger than {} bytes, giving up", carver.name(), carver.max_size());
+                    return Ok(None);
+                }
+                // Length known, read up to there, if there are enough free blocks left
+                Scan::End(length) if length > data.len() + free_blocks.remaining(next_index) * block_size => {
+                    println!("{} runs past the last free block, it is truncated", carver.name());
+                    return Ok(None);
+                }
+                Scan::End(length) => length,
+                Scan::MaybeEnd { end, lookahead } => {
+                    fallback = Some(end);
+                    lookahead.min(carver.max_size())
+                }
+                // The whole limit is read and the end is still not in sight
+                Scan::NeedMore if data.len() >= carver.max_size() => {
+                    println!("{} is bigger than {} bytes, giving up", carver.name(), carver.max_size());
+                    return Ok(None);
+                }
+                // Read twice as much as before, so we don't scan the same data over and over again,
+                // but don't fetch a lot of blocks for a file that may end right behind the data
+                Scan::NeedMore => {
+                    open_end = true;
+                    (data.len() * 2).min(data.len() + MAX_READ_AHEAD).min(carver.max_size())
+                }
+                Scan::Invalid => return Ok(None),
+            };
+
+            let length_before = data.len();
+            while data.len() < wanted {
+                let (group_number, block_number) = match free_blocks.get(next_index) {
+                    Some(block) => block,
+                    None => break,
+                };
+                let block_data = ext2_fs.read_block(block_number as u32)?;
+
+                // Files with more than 12 blocks have their indirect blocks between the data blocks,
+                // they are not part of the file. Only look for them, where ext2 puts them.
+                let level = ext2_fs.indirect_blocks_before(data_blocks).get(skipped).copied();
+                let following = |i: usize| free_blocks.get(next_index + 1 + i).map(|(_, block_number)| block_number as u32);
+                if level.is_some_and(|level| ext2_fs.looks_like_indirect_block(&block_data, level, following)) {
+                    println!("Skipping indirect block in Block Group {}, Block {}", group_number, block_number);
+                    skipped += 1;
+                } else {
+                    data.extend_from_slice(&block_data);
+                    block_ends.push((data.len(), next_index));
+                    data_blocks += 1;
+                    skipped = 0;
+                }
+                next_index += 1;
+            }
+
+            // No more free blocks, the file is truncated unless it is complete without the rest
+            if data.len() == length_before {
+                let end = if open_end { carver.find_end_at_eof(&data) } else { fallback };
+                match end {
+                    Some(end) if end <= data.len() => {
+                        println!("{} has no certain end, assuming it ends after {} bytes", carver.name(), end);
+                        break end;
+                    }
+                    _ => {
+                        println!("{} is truncated, no end found", carver.name());
+                        return Ok(None);
+                    }
+                }
+            }
+        };
+
+        // The block holding the last byte is the last block of the file
+        let (last_end, last_index) = *block_ends.iter().find(|(end, _)| *end >= length).unwrap();
+        let (group_number, block_number) = free_blocks.get(last_index).unwrap();
+        println!("{} End found in Block Group {}, Block {}", carver.name(), group_number, block_number);
+
+        data.truncate(length);
+        if !carver.validate(&data) {
+            println!("{} failed validation", carver.name());
+            return Ok(None);
+        }
+
+        // Position of the end inside the last block, the data of the first block starts at offset
+        let end_in_block = block_size - (last_end - length);
+        let next = if end_in_block < block_size {
+            (last_index, end_in_block)
+        } else {
+            (last_index + 1, 0)
+        };
+        Ok(Some(CarvedFile { data, next_block: next.0, next_offset: next.1 }))
+    }
+}
+
+// Lookup table for the CRC-32 used by PNG, ZIP and gzip (polynomial 0xEDB88320)
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+};
+
+/// CRC-32 checksum of the data
+pub fn crc32(data: &[u8]) -> u32 {
+    crc32_update(0, data)
+}
+
+/// Continues the CRC-32 of earlier data with more data
+pub fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = crc ^ 0xFFFF_FFFF;
+    for &byte in data {
+        crc = CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    crc ^ 0xFFFF_FFFF
+}
+
+/// Offset of the first occurrence of pattern in data
+pub fn find_bytes(data: &[u8], pattern: &[u8]) -> Option<usize> {
+    data.windows(pattern.len()).position(|window| window == pattern)
+}
+
+/// Little endian numbers at an offset, None if the data is too short
+pub fn le_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().unwrap()))
+}
+
+pub fn le_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().unwrap()))
+}
+
+pub fn le_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(offset..offset + 8)?.try_into().unwrap()))
+}
+
+/// Big endian numbers at an offset, None if the data is too short
+pub fn be_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(offset..offset + 2)?.try_into().unwrap()))
+}
+
+pub fn be_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().unwrap()))
+}
+
+pub fn be_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(offset..offset + 8)?.try_into().unwrap()))
+}
@@ -0,0 +1,218 @@
+use super::Scan;
+
+/// Reads bits starting with the most significant bit of every byte (FLAC, bzip2)
+pub struct MsbBitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> MsbBitReader<'a> {
+    /// Starts reading at the byte offset
+    pub fn new(data: &'a [u8], offset: usize) -> Self {
+        MsbBitReader { data, position: offset * 8 }
+    }
+
+    /// Reads up to 64 bits, None at the end of the data
+    pub fn read(&mut self, bits: u32) -> Option<u64> {
+        let mut value = 0;
+        for _ in 0..bits {
+            let byte = *self.data.get(self.position / 8)?;
+            value = (value << 1) | ((byte >> (7 - self.position % 8)) & 1) as u64;
+            self.position += 1;
+        }
+        Some(value)
+    }
+
+    pub fn skip(&mut self, bits: u64) -> Option<()> {
+        self.position += bits as usize;
+        if self.position > self.data.len() * 8 {
+            return None;
+        }
+        Some(())
+    }
+
+    /// Number of 0 bits before the next 1 bit
+    pub fn unary(&mut self) -> Option<u64> {
+        let mut zeros = 0;
+        while self.read(1)? == 0 {
+            zeros += 1;
+        }
+        Some(zeros)
+    }
+
+    /// Number of 1 bits before the next 0 bit
+    pub fn unary_ones(&mut self) -> Option<usize> {
+        let mut ones = 0;
+        while self.read(1)? == 1 {
+            ones += 1;
+        }
+        Some(ones)
+    }
+
+    /// Bytes read so far, including a partly read last byte
+    pub fn byte_position(&self) -> usize {
+        self.position.div_ceil(8)
+    }
+}
+
+/// Reads bits starting with the least significant bit of every byte (deflate)
+pub struct LsbBitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> LsbBitReader<'a> {
+    /// Starts reading at the byte offset
+    pub fn new(data: &'a [u8], offset: usize) -> Self {
+        LsbBitReader { data, position: offset * 8 }
+    }
+
+    /// Reads up to 32 bits, the first bit read is the lowest bit of the value
+    pub fn read(&mut self, bits: u32) -> Option<u32> {
+        let mut value = 0;
+        for bit in 0..bits {
+            let byte = *self.data.get(self.position / 8)?;
+            value |= (((byte >> (self.position % 8)) & 1) as u32) << bit;
+            self.position += 1;
+        }
+        Some(value)
+    }
+
+    /// Skips the rest of the current byte
+    pub fn align(&mut self) {
+        self.position = self.position.div_ceil(8) * 8;
+    }
+
+    /// Bytes read so far, including a partly read last byte
+    pub fn byte_position(&self) -> usize {
+        self.position.div_ceil(8)
+    }
+}
+
+/// Canonical Huffman code, built from the code length of every symbol like in deflate and bzip2
+pub struct Huffman {
+    // Number of codes of every length
+    counts: Vec<u16>,
+    // Symbols ordered by code
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /// None if the lengths use more codes than there are (over-subscribed)
+    pub fn new(lengths: &[u8]) -> Option<Self> {
+        let max_length = *lengths.iter().max()? as usize;
+        let mut counts = vec![0u16; max_length + 1];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut left = 1i32;
+        for &count in counts[1..].iter() {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return None;
+            }
+        }
+
+        // Within a length, codes are assigned in the order of the symbols
+        let mut offsets = vec![0u16; max_length + 2];
+        for length in 1..=max_length {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0u16; offsets[max_length + 1] as usize];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+        Some(Huffman { counts, symbols })
+    }
+
+    /// Decodes one symbol, reading the code bit by bit with next_bit
+    pub fn decode(&self, mut next_bit: impl FnMut() -> Option<u32>) -> Result<u16, Scan> {
+        // First code and index in symbols of the current length
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in self.counts[1..].iter() {
+            code |= next_bit().ok_or(Scan::NeedMore)? as i32;
+            let count = count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        // Incomplete code, this is not a valid stream
+        Err(Scan::Invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn msb_reads_high_bits_first() {
+        let data = [0b1011_0001, 0b0000_0100];
+        let mut reader = MsbBitReader::new(&data, 0);
+        assert_eq!(reader.read(3), Some(0b101));
+        assert_eq!(reader.unary_ones(), Some(1));
+        assert_eq!(reader.unary(), Some(2));
+        assert_eq!(reader.byte_position(), 1);
+        assert_eq!(reader.read(8), Some(4));
+        assert_eq!(reader.read(1), None);
+    }
+
+    #[test]
+    fn msb_starts_at_offset_and_skips() {
+        let data = [0xFF, 0x0F, 0xF0];
+        let mut reader = MsbBitReader::new(&data, 1);
+        assert_eq!(reader.read(8), Some(0x0F));
+        assert_eq!(reader.skip(4), Some(()));
+        assert_eq!(reader.read(4), Some(0));
+        assert_eq!(reader.skip(1), None);
+    }
+
+    #[test]
+    fn lsb_reads_low_bits_first() {
+        let data = [0b1011_0001, 0xFF];
+        let mut reader = LsbBitReader::new(&data, 0);
+        assert_eq!(reader.read(3), Some(0b001));
+        assert_eq!(reader.read(5), Some(0b10110));
+        assert_eq!(reader.read(4), Some(0xF));
+        assert_eq!(reader.byte_position(), 2);
+        reader.align();
+        assert_eq!(reader.read(1), None);
+    }
+
+    #[test]
+    fn lsb_align_skips_rest_of_byte() {
+        let data = [0x01, 0x34, 0x12];
+        let mut reader = LsbBitReader::new(&data, 0);
+        assert_eq!(reader.read(1), Some(1));
+        reader.align();
+        assert_eq!(reader.byte_position(), 1);
+        assert_eq!(reader.read(16), Some(0x1234));
+    }
+
+    #[test]
+    fn huffman_canonical_codes() {
+        // Example of RFC 1951: A = 10, B = 0, C = 110, D = 111
+        let huffman = Huffman::new(&[2, 1, 3, 3]).unwrap();
+        let mut bits = [0, 1, 0, 1, 1, 0, 1, 1, 1].into_iter();
+        let symbols: Vec<u16> = (0..4).map(|_| huffman.decode(|| bits.next()).ok().unwrap()).collect();
+        assert_eq!(symbols, [1, 0, 2, 3]);
+        assert!(matches!(huffman.decode(|| bits.next()), Err(Scan::NeedMore)));
+    }
+
+    #[test]
+    fn huffman_rejects_bad_codes() {
+        assert!(Huffman::new(&[1, 1, 1]).is_none());
+        // A single code of length 1 leaves the code 1 unused
+        let huffman = Huffman::new(&[0, 1]).unwrap();
+        assert_eq!(huffman.decode(|| Some(0)).ok(), Some(1));
+        assert!(matches!(huffman.decode(|| Some(1)), Err(Scan::Invalid)));
+    }
+}
@@ -0,0 +1,105 @@
+use super::{Carver, Scan};
+
+/// BMP: "BM" file header with the file size, followed by a DIB header
+pub struct BmpCarver;
+
+// Sizes of the known DIB headers: BITMAPCOREHEADER, BITMAPINFOHEADER, V2, V3, OS/2 2.x, V4, V5
+const DIB_HEADER_SIZES: [u32; 7] = [12, 40, 52, 56, 64, 108, 124];
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+
+// The fields of the file header and DIB header we need
+struct BmpHeader {
+    file_size: u32,
+    pixel_offset: u32,
+    dib_size: u32,
+    width: i64,
+    height: i64,
+    bits_per_pixel: u16,
+    compression: u32,
+}
+
+impl BmpCarver {
+    fn parse_header(data: &[u8]) -> Option<BmpHeader> {
+        if data.len() < 26 || &data[..2] != b"BM" {
+            return None;
+        }
+        let u16_at = |offset: usize| u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap());
+        let u32_at = |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+
+        let file_size = u32_at(2);
+        let pixel_offset = u32_at(10);
+        let dib_size = u32_at(14);
+        if !DIB_HEADER_SIZES.contains(&dib_size) || data.len() < 14 + dib_size as usize {
+            return None;
+        }
+
+        // The old core header has 16 bit sizes, all others 32 bit
+        let (width, height, planes, bits_per_pixel, compression) = if dib_size == 12 {
+            (u16_at(18) as i64, u16_at(20) as i64, u16_at(22), u16_at(24), BI_RGB)
+        } else {
+            (u32_at(18) as i32 as i64, u32_at(22) as i32 as i64, u16_at(26), u16_at(28), u32_at(30))
+        };
+
+        // There is only one colour plane, anything else can't be a BMP
+        if planes != 1 {
+            return None;
+        }
+
+        Some(BmpHeader { file_size, pixel_offset, dib_size, width, height, bits_per_pixel, compression })
+    }
+
+    fn is_valid(header: &BmpHeader) -> bool {
+        let headers_end = 14 + header.dib_size;
+        header.width > 0
+            && header.height != 0
+            && [1, 4, 8, 16, 24, 32].contains(&header.bits_per_pixel)
+            && header.compression <= 6
+            && header.pixel_offset >= headers_end
+            && header.pixel_offset < header.file_size
+    }
+}
+
+impl Carver for BmpCarver {
+    fn name(&self) -> &'static str {
+        "BMP"
+    }
+
+    fn extension(&self) -> &'static str {
+        "bmp"
+    }
+
+    fn max_size(&self) -> usize {
+        256 * 1024 * 1024
+    }
+
+    fn find_header(&self, block: &[u8]) -> Option<usize> {
+        match BmpCarver::parse_header(block) {
+            Some(header) if BmpCarver::is_valid(&header) => Some(0),
+            _ => None,
+        }
+    }
+
+    fn find_end(&self, data: &[u8]) -> Scan {
+        // The file header declares the size of the whole file
+        match BmpCarver::parse_header(data) {
+            Some(header) if BmpCarver::is_valid(&header) => Scan::End(header.file_size as usize),
+            _ => Scan::Invalid,
+        }
+    }
+
+    fn validate(&self, data: &[u8]) -> bool {
+        let header = match BmpCarver::parse_header(data) {
+            Some(header) => header,
+            None => return false,
+        };
+        // Uncompressed pixel rows are padded to 4 bytes, the declared size has to hold all of them
+        if header.compression == BI_RGB || header.compression == BI_BITFIELDS {
+            let row_size = (header.bits_per_pixel as u64 * header.width as u64).div_ceil(32) * 4;
+            return row_size.checked_mul(header.height.unsigned_abs())
+                .and_then(|pixel_size| pixel_size.checked_add(header.pixel_offset as u64))
+                .is_some_and(|end| end <= header.file_size as u64);
+        }
+        true
+    }
+}
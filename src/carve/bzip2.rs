@@ -0,0 +1,269 @@
+use super::bits::{Huffman, MsbBitReader};
+use super::{Carver, Scan};
+
+/// bzip2 stream: "BZh" with the block size, compressed blocks and an end of stream marker with
+/// the combined CRC. Blocks are bit aligned and have no length, so the stream is decoded.
+pub struct Bzip2Carver;
+
+const BLOCK_MAGIC: u64 = 0x3141_5926_5359;
+const END_MAGIC: u64 = 0x1772_4538_5090;
+
+// Huffman coded symbols are decoded in groups of 50 with the same table
+const GROUP_SIZE: usize = 50;
+const RUN_A: u16 = 0;
+const RUN_B: u16 = 1;
+
+// Lookup table for the CRC-32 of bzip2, which uses the polynomial 0x04C11DB7 without reflection
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = (n as u32) << 24;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 0x8000_0000 != 0 { (c << 1) ^ 0x04C1_1DB7 } else { c << 1 };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+};
+
+impl Bzip2Carver {
+    // Decodes one block and returns the CRC of its uncompressed data
+    fn decode_block(reader: &mut MsbBitReader, max_block_size: usize) -> Result<u32, Scan> {
+        // Randomised blocks are from versions before 0.9.5, which are not supported
+        if reader.read(1).ok_or(Scan::NeedMore)? != 0 {
+            return Err(Scan::Invalid);
+        }
+        let original_pointer = reader.read(24).ok_or(Scan::NeedMore)? as usize;
+
+        // Bytes used in the block, as a two-level bitmap
+        let mut used_bytes = vec![];
+        let ranges = reader.read(16).ok_or(Scan::NeedMore)?;
+        for range in 0..16 {
+            if ranges & (0x8000 >> range) != 0 {
+                let bytes = reader.read(16).ok_or(Scan::NeedMore)?;
+                used_bytes.extend((0..16).filter(|byte| bytes & (0x8000 >> byte) != 0).map(|byte| (range * 16 + byte) as u8));
+            }
+        }
+        if used_bytes.is_empty() {
+            return Err(Scan::Invalid);
+        }
+        // RUNA, RUNB, the move-to-front indexes 1.. and end of block
+        let alphabet_size = used_bytes.len() + 2;
+
+        // Which table is used for each group of 50 symbols, move-to-front coded
+        let table_count = reader.read(3).ok_or(Scan::NeedMore)? as usize;
+        let selector_count = reader.read(15).ok_or(Scan::NeedMore)? as usize;
+        if !(2..=6).contains(&table_count) || selector_count == 0 {
+            return Err(Scan::Invalid);
+        }
+        let mut table_order: Vec<usize> = (0..table_count).collect();
+        let mut selectors = Vec::with_capacity(selector_count);
+        for _ in 0..selector_count {
+            let index = reader.unary_ones().ok_or(Scan::NeedMore)?;
+            if index >= table_count {
+                return Err(Scan::Invalid);
+            }
+            let table = table_order.remove(index);
+            table_order.insert(0, table);
+            selectors.push(table);
+        }
+
+        // Code lengths of every table, delta coded
+        let mut tables = vec![];
+        for _ in 0..table_count {
+            let mut length = reader.read(5).ok_or(Scan::NeedMore)? as i32;
+            let mut lengths = vec![];
+            for _ in 0..alphabet_size {
+                loop {
+                    if !(1..=20).contains(&length) {
+                        return Err(Scan::Invalid);
+                    }
+                    if reader.read(1).ok_or(Scan::NeedMore)? == 0 {
+                        break;
+                    }
+                    length += if reader.read(1).ok_or(Scan::NeedMore)? == 0 { 1 } else { -1 };
+                }
+                lengths.push(length as u8);
+            }
+            tables.push(Huffman::new(&lengths).ok_or(Scan::Invalid)?);
+        }
+
+        // Move-to-front indexes with runs of the first byte coded as RUNA/RUNB numbers
+        let end_of_block = (alphabet_size - 1) as u16;
+        let mut move_to_front = used_bytes;
+        let mut block = vec![];
+        let (mut run, mut run_weight) = (0usize, 1usize);
+        for symbol_index in 0.. {
+            let table = &tables[*selectors.get(symbol_index / GROUP_SIZE).ok_or(Scan::Invalid)?];
+            let symbol = table.decode(|| reader.read(1).map(|bit| bit as u32))?;
+
+            if symbol == RUN_A || symbol == RUN_B {
+                run += run_weight << symbol;
+                run_weight <<= 1;
+                if run > max_block_size {
+                    return Err(Scan::Invalid);
+                }
+                continue;
+            }
+            if run > 0 {
+                block.extend(std::iter::repeat_n(move_to_front[0], run));
+                (run, run_weight) = (0, 1);
+            }
+            if symbol == end_of_block {
+                break;
+            }
+            let byte = move_to_front.remove(symbol as usize - 1);
+            move_to_front.insert(0, byte);
+            block.push(byte);
+            if block.len() > max_block_size {
+                return Err(Scan::Invalid);
+            }
+        }
+        if original_pointer >= block.len() {
+            return Err(Scan::Invalid);
+        }
+
+        Ok(Bzip2Carver::block_crc(&block, original_pointer))
+    }
+
+    // Undoes the Burrows-Wheeler transform and the run length encoding of runs of 4-255 bytes,
+    // the CRC is calculated over the result
+    fn block_crc(block: &[u8], original_pointer: usize) -> u32 {
+        // Position of every byte in the sorted first column
+        let mut starts = [0usize; 256];
+        for &byte in block {
+            starts[byte as usize] += 1;
+        }
+        let mut sum = 0;
+        for start in starts.iter_mut() {
+            (*start, sum) = (sum, sum + *start);
+        }
+        let mut next = vec![0usize; block.len()];
+        for (index, &byte) in block.iter().enumerate() {
+            next[starts[byte as usize]] = index;
+            starts[byte as usize] += 1;
+        }
+
+        let mut crc = 0xFFFF_FFFFu32;
+        let mut update = |byte: u8| crc = (crc << 8) ^ CRC_TABLE[((crc >> 24) as u8 ^ byte) as usize];
+        let mut position = next[original_pointer];
+        let (mut previous, mut repeated) = (None, 0);
+        for _ in 0..block.len() {
+            let byte = block[position];
+            position = next[position];
+            // After 4 equal bytes the next byte is the number of further repetitions
+            if repeated == 4 {
+                for _ in 0..byte {
+                    update(previous.unwrap());
+                }
+                (previous, repeated) = (None, 0);
+                continue;
+            }
+            if previous == Some(byte) {
+                repeated += 1;
+            } else {
+                (previous, repeated) = (Some(byte), 1);
+            }
+            update(byte);
+        }
+        !crc
+    }
+}
+
+impl Carver for Bzip2Carver {
+    fn name(&self) -> &'static str {
+        "bzip2"
+    }
+
+    fn extension(&self) -> &'static str {
+        "bz2"
+    }
+
+    fn max_size(&self) -> usize {
+        512 * 1024 * 1024
+    }
+
+    fn find_header(&self, block: &[u8]) -> Option<usize> {
+        // "BZh", block size in 100k, the first block
+        if block.len() >= 10 && block.starts_with(b"BZh") && (b'1'..=b'9').contains(&block[3]) && block[4..10] == BLOCK_MAGIC.to_be_bytes()[2..] {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    fn find_end(&self, data: &[u8]) -> Scan {
+        let max_block_size = (data[3] - b'0') as usize * 100_000;
+        let mut reader = MsbBitReader::new(data, 4);
+        let mut combined_crc = 0u32;
+        loop {
+            let magic = match reader.read(48) {
+                Some(magic) => magic,
+                None => return Scan::NeedMore,
+            };
+            let crc = match reader.read(32) {
+                Some(crc) => crc as u32,
+                None => return Scan::NeedMore,
+            };
+            match magic {
+                BLOCK_MAGIC => match Bzip2Carver::decode_block(&mut reader, max_block_size) {
+                    Ok(block_crc) if block_crc == crc => combined_crc = combined_crc.rotate_left(1) ^ crc,
+                    Ok(_) => return Scan::Invalid,
+                    Err(scan) => return scan,
+                },
+                // The stream ends with the CRC of all blocks, padded to a full byte
+                END_MAGIC if crc == combined_crc => return Scan::End(reader.byte_position()),
+                _ => return Scan::Invalid,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // bzip2 of "hello bzip2\n"
+    const HELLO: [u8; 55] = [
+        0x42, 0x5A, 0x68, 0x39, 0x31, 0x41, 0x59, 0x26, 0x53, 0x59, 0xAB, 0x6B, 0xA1, 0xF1, 0x00, 0x00,
+        0x02, 0xD9, 0x80, 0x00, 0x10, 0x40, 0x00, 0x10, 0x00, 0x12, 0x64, 0xC0, 0x10, 0x20, 0x00, 0x31,
+        0x00, 0xD3, 0x4D, 0x04, 0x00, 0x1E, 0xA3, 0xEF, 0x4E, 0x51, 0xA2, 0x07, 0x8B, 0xB9, 0x22, 0x9C,
+        0x28, 0x48, 0x55, 0xB5, 0xD0, 0xF8, 0x80,
+    ];
+    // bzip2 of "aaaaaaaaaab\n", the run of 10 is length encoded
+    const RUN: [u8; 43] = [
+        0x42, 0x5A, 0x68, 0x39, 0x31, 0x41, 0x59, 0x26, 0x53, 0x59, 0x0F, 0xA4, 0x0C, 0xBF, 0x00, 0x00,
+        0x02, 0xC1, 0x00, 0x01, 0x10, 0x30, 0x00, 0x20, 0x00, 0x21, 0x9A, 0x68, 0x33, 0x4D, 0x32, 0x55,
+        0xE2, 0xEE, 0x48, 0xA7, 0x0A, 0x12, 0x01, 0xF4, 0x81, 0x97, 0xE0,
+    ];
+
+    #[test]
+    fn one_block_stream() {
+        assert!(Bzip2Carver.find_header(&HELLO).is_some());
+        assert!(matches!(Bzip2Carver.find_end(&HELLO), Scan::End(55)));
+        assert!(matches!(Bzip2Carver.find_end(&RUN), Scan::End(43)));
+    }
+
+    #[test]
+    fn truncated_stream() {
+        assert!(matches!(Bzip2Carver.find_end(&HELLO[..30]), Scan::NeedMore));
+    }
+
+    #[test]
+    fn wrong_block_crc() {
+        let mut data = HELLO;
+        data[10] ^= 1;
+        assert!(matches!(Bzip2Carver.find_end(&data), Scan::Invalid));
+    }
+
+    #[test]
+    fn wrong_combined_crc() {
+        let mut data = RUN;
+        data[40] ^= 1;
+        assert!(matches!(Bzip2Carver.find_end(&data), Scan::Invalid));
+    }
+}
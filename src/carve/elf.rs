@@ -0,0 +1,171 @@
+use super::{be_u16, be_u32, be_u64, le_u16, le_u32, le_u64, Carver, Scan};
+
+/// ELF executables, shared libraries and object files, 32 and 64 bit in both byte orders.
+/// The file ends with the last of its headers, segments and sections.
+pub struct ElfCarver;
+
+const ELF_MAGIC: &[u8] = b"\x7FELF";
+const ELFCLASS32: u8 = 1;
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const ELFDATA2MSB: u8 = 2;
+const ET_REL: u16 = 1;
+const ET_CORE: u16 = 4;
+// Sections like .bss take no space in the file
+const SHT_NOBITS: u32 = 8;
+
+// Reads the fields of the file, in its byte order and with the size of its class
+struct ElfReader<'a> {
+    data: &'a [u8],
+    is_64: bool,
+    little_endian: bool,
+}
+
+impl ElfReader<'_> {
+    fn new(data: &[u8]) -> Option<ElfReader<'_>> {
+        if !data.starts_with(ELF_MAGIC) || data.len() < 64 {
+            return None;
+        }
+        let is_64 = match data[4] {
+            ELFCLASS32 => false,
+            ELFCLASS64 => true,
+            _ => return None,
+        };
+        let little_endian = match data[5] {
+            ELFDATA2LSB => true,
+            ELFDATA2MSB => false,
+            _ => return None,
+        };
+        Some(ElfReader { data, is_64, little_endian })
+    }
+
+    fn half(&self, offset: usize) -> Option<u16> {
+        if self.little_endian { le_u16(self.data, offset) } else { be_u16(self.data, offset) }
+    }
+
+    fn word(&self, offset: usize) -> Option<u32> {
+        if self.little_endian { le_u32(self.data, offset) } else { be_u32(self.data, offset) }
+    }
+
+    // Addresses, offsets and sizes have 32 or 64 bits
+    fn offset(&self, offset: usize) -> Option<usize> {
+        let value = match (self.is_64, self.little_endian) {
+            (true, true) => le_u64(self.data, offset)?,
+            (true, false) => be_u64(self.data, offset)?,
+            (false, _) => self.word(offset)? as u64,
+        };
+        usize::try_from(value).ok()
+    }
+
+    // Picks the field offset of the class
+    fn field(&self, offset_32: usize, offset_64: usize) -> usize {
+        if self.is_64 { offset_64 } else { offset_32 }
+    }
+
+    fn file_type(&self) -> Option<u16> {
+        self.half(16)
+    }
+
+    // Offset, entry size and number of entries of the program and the section header table
+    fn tables(&self) -> Option<[(usize, usize, usize); 2]> {
+        let program_headers = (self.offset(self.field(28, 32))?, self.half(self.field(42, 54))? as usize, self.half(self.field(44, 56))? as usize);
+        let section_headers = (self.offset(self.field(32, 40))?, self.half(self.field(46, 58))? as usize, self.half(self.field(48, 60))? as usize);
+        Some([program_headers, section_headers])
+    }
+
+    // Checks the header: version, sizes of the header and the table entries of the class
+    fn is_valid_header(&self) -> bool {
+        let (header_size, program_header_size, section_header_size) = if self.is_64 { (64, 56, 64) } else { (52, 32, 40) };
+        let [(_, program_entry, program_count), (_, section_entry, section_count)] = match self.tables() {
+            Some(tables) => tables,
+            None => return false,
+        };
+        self.data[6] == 1
+            && self.file_type().is_some_and(|file_type| (ET_REL..=ET_CORE).contains(&file_type))
+            && self.half(18).is_some_and(|machine| machine != 0)
+            && self.word(20) == Some(1)
+            && self.half(self.field(40, 52)) == Some(header_size)
+            && (program_count == 0 || program_entry == program_header_size)
+            && (section_count == 0 || section_entry == section_header_size)
+            && self.half(self.field(50, 62)).is_some_and(|names| section_count == 0 || (names as usize) < section_count)
+    }
+
+    // End of the file: the header tables, every segment and every section with data in the file.
+    // The tables are read first, then the segments and sections they describe.
+    fn extent(&self) -> Scan {
+        let tables = match self.tables() {
+            Some(tables) => tables,
+            None => return Scan::Invalid,
+        };
+        let mut end = self.half(self.field(40, 52)).unwrap_or(0) as usize;
+        for (offset, entry_size, count) in tables {
+            match offset.checked_add(entry_size * count) {
+                Some(table_end) => end = end.max(table_end),
+                None => return Scan::Invalid,
+            }
+        }
+        if end > self.data.len() {
+            return Scan::End(end);
+        }
+
+        // (table, offset of the offset and the size field of an entry, type of entries without data)
+        let [program_headers, section_headers] = tables;
+        let entries = [
+            (program_headers, self.field(4, 8), self.field(16, 32), None),
+            (section_headers, self.field(16, 24), self.field(20, 32), Some(SHT_NOBITS)),
+        ];
+        for ((table_offset, entry_size, count), offset_field, size_field, without_data) in entries {
+            for index in 0..count {
+                let entry = table_offset + index * entry_size;
+                if without_data.is_some() && self.word(entry + 4) == without_data {
+                    continue;
+                }
+                match (self.offset(entry + offset_field), self.offset(entry + size_field)) {
+                    (Some(offset), Some(size)) => match offset.checked_add(size) {
+                        Some(data_end) => end = end.max(data_end),
+                        None => return Scan::Invalid,
+                    },
+                    _ => return Scan::Invalid,
+                }
+            }
+        }
+        Scan::End(end)
+    }
+}
+
+impl Carver for ElfCarver {
+    fn name(&self) -> &'static str {
+        "ELF"
+    }
+
+    fn extension(&self) -> &'static str {
+        "elf"
+    }
+
+    fn extension_for(&self, data: &[u8]) -> &'static str {
+        match ElfReader::new(data).and_then(|reader| reader.file_type()) {
+            Some(ET_REL) => "o",
+            Some(ET_CORE) => "core",
+            _ => self.extension(),
+        }
+    }
+
+    fn max_size(&self) -> usize {
+        512 * 1024 * 1024
+    }
+
+    fn find_header(&self, block: &[u8]) -> Option<usize> {
+        match ElfReader::new(block) {
+            Some(reader) if reader.is_valid_header() => Some(0),
+            _ => None,
+        }
+    }
+
+    fn find_end(&self, data: &[u8]) -> Scan {
+        let reader = match ElfReader::new(data) {
+            Some(reader) => reader,
+            None => return Scan::Invalid,
+        };
+        reader.extent()
+    }
+}
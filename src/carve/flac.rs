@@ -0,0 +1,301 @@
+use super::bits::MsbBitReader;
+use super::{be_u16, Carver, Scan};
+
+/// FLAC: "fLaC", metadata blocks starting with STREAMINFO, then audio frames.
+/// Frames have no length field, the subframes are walked to find the end of each frame.
+pub struct FlacCarver;
+
+const STREAMINFO: u8 = 0;
+const STREAMINFO_SIZE: usize = 34;
+const INVALID_BLOCK_TYPE: u8 = 127;
+
+// The values of STREAMINFO we need to find the end
+struct StreamInfo {
+    bits_per_sample: u32,
+    total_samples: u64,
+}
+
+// A frame header: number of samples, bits per sample of every channel and length of the header
+struct FrameHeader {
+    block_size: u64,
+    channel_bits: Vec<u32>,
+    length: usize,
+}
+
+// CRC-8 of the frame header, polynomial x^8 + x^2 + x + 1
+fn crc8(data: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for &byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+// CRC-16 of a frame, polynomial x^16 + x^15 + x^2 + 1
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x8005 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+impl FlacCarver {
+    fn stream_info(data: &[u8]) -> Option<StreamInfo> {
+        // Block header: last block flag and type, 24 bit length
+        if !data.starts_with(b"fLaC") || data.get(4)? & 0x7F != STREAMINFO || data.get(5..8)? != [0, 0, STREAMINFO_SIZE as u8] {
+            return None;
+        }
+        let info = data.get(8..8 + STREAMINFO_SIZE)?;
+        let min_block_size = be_u16(info, 0)?;
+        let max_block_size = be_u16(info, 2)?;
+        // 20 bits sample rate, 3 bits channels, 5 bits bits per sample, 36 bits total samples
+        let sample_rate = u32::from_be_bytes([0, info[10], info[11], info[12]]) >> 4;
+        let bits_per_sample = ((((info[12] & 0x01) << 4) | (info[13] >> 4)) + 1) as u32;
+        let total_samples = u64::from_be_bytes([0, 0, 0, info[13] & 0x0F, info[14], info[15], info[16], info[17]]);
+        if min_block_size < 16 || max_block_size < min_block_size || sample_rate == 0 || bits_per_sample < 4 {
+            return None;
+        }
+        Some(StreamInfo { bits_per_sample, total_samples })
+    }
+
+    // Parses the frame header at the start of data, the CRC-8 at its end has to match
+    fn frame_header(data: &[u8], info: &StreamInfo) -> Option<FrameHeader> {
+        // Sync code and blocking strategy, reserved bits are 0
+        if data.len() < 6 || data[0] != 0xFF || data[1] & 0xFE != 0xF8 || data[3] & 0x01 != 0 {
+            return None;
+        }
+        let block_size_code = data[2] >> 4;
+        let sample_rate_code = data[2] & 0x0F;
+        let channel_assignment = data[3] >> 4;
+        let bits_per_sample = match (data[3] >> 1) & 0x07 {
+            0 => info.bits_per_sample,
+            1 => 8,
+            2 => 12,
+            4 => 16,
+            5 => 20,
+            6 => 24,
+            7 => 32,
+            _ => return None,
+        };
+        // Independent channels, or stereo with a side channel, which has one bit more
+        let channel_bits = match channel_assignment {
+            0..=7 => vec![bits_per_sample; channel_assignment as usize + 1],
+            8 => vec![bits_per_sample, bits_per_sample + 1],
+            9 => vec![bits_per_sample + 1, bits_per_sample],
+            10 => vec![bits_per_sample, bits_per_sample + 1],
+            _ => return None,
+        };
+        if block_size_code == 0 || sample_rate_code == 15 {
+            return None;
+        }
+
+        // Frame or sample number, coded like UTF-8 with up to 7 bytes
+        let mut length = 4 + match data[4] {
+            0x00..=0x7F => 1,
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            0xF8..=0xFB => 5,
+            0xFC..=0xFD => 6,
+            0xFE => 7,
+            _ => return None,
+        };
+        let block_size = match block_size_code {
+            1 => 192,
+            2..=5 => 576 << (block_size_code - 2),
+            6 => {
+                length += 1;
+                *data.get(length - 1)? as u64 + 1
+            }
+            7 => {
+                length += 2;
+                be_u16(data, length - 2)? as u64 + 1
+            }
+            _ => 256 << (block_size_code - 8),
+        };
+        length += match sample_rate_code {
+            12 => 1,
+            13 | 14 => 2,
+            _ => 0,
+        };
+
+        if crc8(data.get(..length)?) != *data.get(length)? {
+            return None;
+        }
+        Some(FrameHeader { block_size, channel_bits, length: length + 1 })
+    }
+
+    // Walks one subframe, Err(Scan::NeedMore) if it is not complete yet, Err(Scan::Invalid) if it is broken
+    fn skip_subframe(reader: &mut MsbBitReader, block_size: u64, bits_per_sample: u32) -> Result<(), Scan> {
+        // Zero bit, type, flag for wasted bits per sample
+        let header = reader.read(8).ok_or(Scan::NeedMore)?;
+        if header & 0x80 != 0 {
+            return Err(Scan::Invalid);
+        }
+        let wasted = if header & 0x01 != 0 { reader.unary().ok_or(Scan::NeedMore)? + 1 } else { 0 };
+        if wasted >= bits_per_sample as u64 {
+            return Err(Scan::Invalid);
+        }
+        let bits = bits_per_sample as u64 - wasted;
+
+        let order = match (header >> 1) & 0x3F {
+            // Constant: one sample
+            0 => return reader.skip(bits).ok_or(Scan::NeedMore),
+            // Verbatim: all samples
+            1 => return reader.skip(bits * block_size).ok_or(Scan::NeedMore),
+            // Fixed predictor: warm-up samples, then the residual
+            fixed @ 8..=12 => {
+                let order = fixed - 8;
+                reader.skip(bits * order).ok_or(Scan::NeedMore)?;
+                order
+            }
+            // Linear predictor: warm-up samples, precision, shift, coefficients, then the residual
+            lpc @ 32..=63 => {
+                let order = lpc - 31;
+                reader.skip(bits * order).ok_or(Scan::NeedMore)?;
+                let precision = reader.read(4).ok_or(Scan::NeedMore)?;
+                if precision == 0x0F {
+                    return Err(Scan::Invalid);
+                }
+                reader.skip(5 + (precision + 1) * order).ok_or(Scan::NeedMore)?;
+                order
+            }
+            _ => return Err(Scan::Invalid),
+        };
+        if order > block_size {
+            return Err(Scan::Invalid);
+        }
+
+        // Rice coded residual in 2^partition_order partitions, with 4 or 5 bit parameters
+        let parameter_bits = match reader.read(2).ok_or(Scan::NeedMore)? {
+            0 => 4,
+            1 => 5,
+            _ => return Err(Scan::Invalid),
+        };
+        let partition_order = reader.read(4).ok_or(Scan::NeedMore)?;
+        let partitions = 1u64 << partition_order;
+        if !block_size.is_multiple_of(partitions) || block_size / partitions < order {
+            return Err(Scan::Invalid);
+        }
+        let escape = (1 << parameter_bits) - 1;
+        for partition in 0..partitions {
+            let samples = block_size / partitions - if partition == 0 { order } else { 0 };
+            let parameter = reader.read(parameter_bits).ok_or(Scan::NeedMore)?;
+            if parameter == escape {
+                // Unencoded samples with a fixed number of bits
+                let raw_bits = reader.read(5).ok_or(Scan::NeedMore)?;
+                reader.skip(raw_bits * samples).ok_or(Scan::NeedMore)?;
+            } else {
+                for _ in 0..samples {
+                    reader.unary().ok_or(Scan::NeedMore)?;
+                    reader.skip(parameter).ok_or(Scan::NeedMore)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // End of the frame at offset: the subframes of all channels, padding to a full byte and the CRC-16
+    fn frame_end(data: &[u8], offset: usize, header: &FrameHeader) -> Scan {
+        let mut reader = MsbBitReader::new(&data[offset..], header.length);
+        for &bits in header.channel_bits.iter() {
+            if let Err(scan) = FlacCarver::skip_subframe(&mut reader, header.block_size, bits) {
+                return scan;
+            }
+        }
+        let end = offset + reader.byte_position() + 2;
+        if end > data.len() {
+            return Scan::NeedMore;
+        }
+        // The CRC-16 over the frame including its own CRC is 0
+        if crc16(&data[offset..end]) != 0 {
+            return Scan::Invalid;
+        }
+        Scan::End(end)
+    }
+
+    // Finds the end of the stream. At the end of the free blocks (at_eof), a stream without a known number
+    // of samples ends after its last complete frame.
+    fn scan(data: &[u8], at_eof: bool) -> Scan {
+        let info = match FlacCarver::stream_info(data) {
+            Some(info) => info,
+            None => return Scan::Invalid,
+        };
+
+        // Metadata blocks up to the one with the last block flag
+        let mut offset = 4;
+        loop {
+            if offset + 4 > data.len() {
+                return Scan::NeedMore;
+            }
+            let header = data[offset];
+            if header & 0x7F == INVALID_BLOCK_TYPE {
+                return Scan::Invalid;
+            }
+            offset += 4 + u32::from_be_bytes([0, data[offset + 1], data[offset + 2], data[offset + 3]]) as usize;
+            if header & 0x80 != 0 {
+                break;
+            }
+        }
+
+        // Frames until all samples are there. Encoders writing to a stream don't know the
+        // number of samples, there the file ends, where no frame follows.
+        let mut samples = 0;
+        loop {
+            if info.total_samples != 0 && samples >= info.total_samples {
+                return Scan::End(offset);
+            }
+            let open_end = at_eof && samples > 0 && info.total_samples == 0;
+            if offset + 16 > data.len() {
+                return if open_end { Scan::End(offset) } else { Scan::NeedMore };
+            }
+            let header = match FlacCarver::frame_header(&data[offset..], &info) {
+                Some(header) => header,
+                None if samples > 0 && info.total_samples == 0 => return Scan::End(offset),
+                None => return Scan::Invalid,
+            };
+            offset = match FlacCarver::frame_end(data, offset, &header) {
+                Scan::End(end) => end,
+                Scan::NeedMore if open_end => return Scan::End(offset),
+                other => return other,
+            };
+            samples += header.block_size;
+        }
+    }
+}
+
+impl Carver for FlacCarver {
+    fn name(&self) -> &'static str {
+        "FLAC"
+    }
+
+    fn extension(&self) -> &'static str {
+        "flac"
+    }
+
+    fn max_size(&self) -> usize {
+        500 * 1024 * 1024
+    }
+
+    fn find_header(&self, block: &[u8]) -> Option<usize> {
+        FlacCarver::stream_info(block).map(|_| 0)
+    }
+
+    fn find_end(&self, data: &[u8]) -> Scan {
+        FlacCarver::scan(data, false)
+    }
+
+    fn find_end_at_eof(&self, data: &[u8]) -> Option<usize> {
+        match FlacCarver::scan(data, true) {
+            Scan::End(end) => Some(end),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use super::{Carver, Scan};
+
+/// GIF87a / GIF89a: header, logical screen descriptor, optional global colour table,
+/// then extensions and images made of sub-blocks, until the trailer 0x3B
+pub struct GifCarver;
+
+const EXTENSION: u8 = 0x21;
+const IMAGE_DESCRIPTOR: u8 = 0x2C;
+const TRAILER: u8 = 0x3B;
+
+impl GifCarver {
+    // Size of a colour table, if the flag in the packed field is set
+    fn color_table_size(packed: u8) -> usize {
+        if packed & 0x80 != 0 {
+            3 * (1 << ((packed & 0x07) + 1))
+        } else {
+            0
+        }
+    }
+
+    // Skips a chain of sub-blocks (size byte, data) up to and including the terminating zero size
+    fn skip_sub_blocks(data: &[u8], mut offset: usize) -> Option<usize> {
+        loop {
+            let size = *data.get(offset)? as usize;
+            offset += 1 + size;
+            if size == 0 {
+                return Some(offset);
+            }
+        }
+    }
+
+    // Walks the blocks up to the trailer, and tells if an image descriptor was among them
+    fn walk(data: &[u8]) -> (Scan, bool) {
+        // Header (6) and logical screen descriptor (7)
+        if data.len() < 13 {
+            return (Scan::NeedMore, false);
+        }
+        let mut offset = 13 + GifCarver::color_table_size(data[10]);
+        let mut has_image = false;
+
+        loop {
+            let introducer = match data.get(offset) {
+                Some(&introducer) => introducer,
+                None => return (Scan::NeedMore, has_image),
+            };
+            let next = match introducer {
+                TRAILER => return (Scan::End(offset + 1), has_image),
+                // Introducer, label, sub-blocks
+                EXTENSION => GifCarver::skip_sub_blocks(data, offset + 2),
+                IMAGE_DESCRIPTOR => {
+                    has_image = true;
+                    // Position and size (8 bytes), packed field, optional local colour table,
+                    // LZW minimum code size, sub-blocks with the image data
+                    let packed = match data.get(offset + 9) {
+                        Some(&packed) => packed,
+                        None => return (Scan::NeedMore, has_image),
+                    };
+                    let lzw_offset = offset + 10 + GifCarver::color_table_size(packed);
+                    match data.get(lzw_offset) {
+                        Some(&code_size) if !(1..=11).contains(&code_size) => return (Scan::Invalid, has_image),
+                        Some(_) => GifCarver::skip_sub_blocks(data, lzw_offset + 1),
+                        None => None,
+                    }
+                }
+                _ => return (Scan::Invalid, has_image),
+            };
+            match next {
+                Some(next) => offset = next,
+                None => return (Scan::NeedMore, has_image),
+            }
+        }
+    }
+}
+
+impl Carver for GifCarver {
+    fn name(&self) -> &'static str {
+        "GIF"
+    }
+
+    fn extension(&self) -> &'static str {
+        "gif"
+    }
+
+    fn max_size(&self) -> usize {
+        50 * 1024 * 1024
+    }
+
+    fn find_header(&self, block: &[u8]) -> Option<usize> {
+        if block.len() < 13 || (&block[..6] != b"GIF87a" && &block[..6] != b"GIF89a") {
+            return None;
+        }
+        // The logical screen can't be empty
+        let width = u16::from_le_bytes([block[6], block[7]]);
+        let height = u16::from_le_bytes([block[8], block[9]]);
+        if width == 0 || height == 0 {
+            return None;
+        }
+        Some(0)
+    }
+
+    fn find_end(&self, data: &[u8]) -> Scan {
+        GifCarver::walk(data).0
+    }
+
+    fn validate(&self, data: &[u8]) -> bool {
+        // Without an image there is nothing to recover
+        GifCarver::walk(data).1
+    }
+}
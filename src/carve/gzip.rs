@@ -0,0 +1,312 @@
+use super::bits::{Huffman, LsbBitReader};
+use super::{crc32_update, le_u16, le_u32, Carver, Scan};
+
+/// gzip member: 10 byte header with optional fields, a deflate stream, then CRC-32 and size
+/// of the uncompressed data. The end of the deflate stream is only known after decoding it.
+pub struct GzipCarver;
+
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+
+// Base values and extra bits of the length symbols 257..285 and the distance symbols
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+// Order of the code length code lengths in a dynamic block
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+const WINDOW_SIZE: usize = 32 * 1024;
+
+// The uncompressed data: only the last 32 KiB are kept for back references,
+// the CRC-32 and the size are calculated on the way
+struct Output {
+    window: Vec<u8>,
+    // Bytes of window already in the CRC
+    checked: usize,
+    crc: u32,
+    size: u64,
+}
+
+impl Output {
+    fn push(&mut self, byte: u8) {
+        self.window.push(byte);
+        self.size += 1;
+        // A single block can hold megabytes, the window must not grow with it
+        if self.window.len() > 2 * WINDOW_SIZE {
+            self.flush();
+        }
+    }
+
+    // Copies length bytes from distance bytes back
+    fn copy(&mut self, distance: usize, length: usize) -> Result<(), Scan> {
+        if distance > self.window.len() {
+            return Err(Scan::Invalid);
+        }
+        for _ in 0..length {
+            self.push(self.window[self.window.len() - distance]);
+        }
+        Ok(())
+    }
+
+    // Adds the new bytes to the CRC and drops what is no longer needed
+    fn flush(&mut self) {
+        self.crc = crc32_update(self.crc, &self.window[self.checked..]);
+        let keep_from = self.window.len().saturating_sub(WINDOW_SIZE);
+        self.window.drain(..keep_from);
+        self.checked = self.window.len();
+    }
+}
+
+impl GzipCarver {
+    // Length of the header with all optional fields
+    fn header_length(data: &[u8]) -> Result<usize, Scan> {
+        let flags = *data.get(3).ok_or(Scan::NeedMore)?;
+        let mut offset = 10;
+        if flags & FEXTRA != 0 {
+            offset += 2 + le_u16(data, offset).ok_or(Scan::NeedMore)? as usize;
+        }
+        // Zero terminated file name and comment
+        for flag in [FNAME, FCOMMENT] {
+            if flags & flag != 0 {
+                let rest = data.get(offset..).ok_or(Scan::NeedMore)?;
+                offset += rest.iter().position(|&byte| byte == 0).ok_or(Scan::NeedMore)? + 1;
+            }
+        }
+        if flags & FHCRC != 0 {
+            offset += 2;
+        }
+        Ok(offset)
+    }
+
+    // Decodes deflate blocks up to the last one
+    fn inflate(reader: &mut LsbBitReader, output: &mut Output) -> Result<(), Scan> {
+        loop {
+            let last = reader.read(1).ok_or(Scan::NeedMore)?;
+            match reader.read(2).ok_or(Scan::NeedMore)? {
+                0 => GzipCarver::stored_block(reader, output)?,
+                1 => {
+                    let (literals, distances) = GzipCarver::fixed_codes();
+                    GzipCarver::compressed_block(reader, output, &literals, &distances)?;
+                }
+                2 => {
+                    let (literals, distances) = GzipCarver::dynamic_codes(reader)?;
+                    GzipCarver::compressed_block(reader, output, &literals, &distances)?;
+                }
+                _ => return Err(Scan::Invalid),
+            }
+            output.flush();
+            if last == 1 {
+                return Ok(());
+            }
+        }
+    }
+
+    // Uncompressed block: length and its one's complement, then the bytes
+    fn stored_block(reader: &mut LsbBitReader, output: &mut Output) -> Result<(), Scan> {
+        reader.align();
+        let length = reader.read(16).ok_or(Scan::NeedMore)?;
+        let complement = reader.read(16).ok_or(Scan::NeedMore)?;
+        if length != !complement & 0xFFFF {
+            return Err(Scan::Invalid);
+        }
+        for _ in 0..length {
+            output.push(reader.read(8).ok_or(Scan::NeedMore)? as u8);
+        }
+        Ok(())
+    }
+
+    fn fixed_codes() -> (Huffman, Huffman) {
+        let mut lengths = [8u8; 288];
+        lengths[144..256].fill(9);
+        lengths[256..280].fill(7);
+        (Huffman::new(&lengths).unwrap(), Huffman::new(&[5; 30]).unwrap())
+    }
+
+    // The code lengths of the literal/length and distance codes, themselves Huffman coded
+    fn dynamic_codes(reader: &mut LsbBitReader) -> Result<(Huffman, Huffman), Scan> {
+        let literal_count = reader.read(5).ok_or(Scan::NeedMore)? as usize + 257;
+        let distance_count = reader.read(5).ok_or(Scan::NeedMore)? as usize + 1;
+        let code_length_count = reader.read(4).ok_or(Scan::NeedMore)? as usize + 4;
+        if literal_count > 286 || distance_count > 30 {
+            return Err(Scan::Invalid);
+        }
+
+        let mut code_length_lengths = [0u8; 19];
+        for &symbol in CODE_LENGTH_ORDER[..code_length_count].iter() {
+            code_length_lengths[symbol] = reader.read(3).ok_or(Scan::NeedMore)? as u8;
+        }
+        let code_lengths = Huffman::new(&code_length_lengths).ok_or(Scan::Invalid)?;
+
+        let mut lengths = vec![];
+        while lengths.len() < literal_count + distance_count {
+            let symbol = code_lengths.decode(|| reader.read(1))?;
+            // 16: repeat the previous length 3-6 times, 17 and 18: 3-10 and 11-138 zeros
+            let (length, repeat) = match symbol {
+                0..=15 => (symbol as u8, 1),
+                16 => (*lengths.last().ok_or(Scan::Invalid)?, 3 + reader.read(2).ok_or(Scan::NeedMore)?),
+                17 => (0, 3 + reader.read(3).ok_or(Scan::NeedMore)?),
+                _ => (0, 11 + reader.read(7).ok_or(Scan::NeedMore)?),
+            };
+            lengths.extend(std::iter::repeat_n(length, repeat as usize));
+        }
+        if lengths.len() > literal_count + distance_count || lengths[256] == 0 {
+            return Err(Scan::Invalid);
+        }
+
+        let literals = Huffman::new(&lengths[..literal_count]).ok_or(Scan::Invalid)?;
+        let distances = Huffman::new(&lengths[literal_count..]).ok_or(Scan::Invalid)?;
+        Ok((literals, distances))
+    }
+
+    // Literals, and lengths with distances as back references, up to the end of block symbol
+    fn compressed_block(reader: &mut LsbBitReader, output: &mut Output, literals: &Huffman, distances: &Huffman) -> Result<(), Scan> {
+        loop {
+            let symbol = literals.decode(|| reader.read(1))? as usize;
+            match symbol {
+                0..=255 => output.push(symbol as u8),
+                256 => return Ok(()),
+                257..=285 => {
+                    let index = symbol - 257;
+                    let length = LENGTH_BASE[index] as usize + reader.read(LENGTH_EXTRA[index] as u32).ok_or(Scan::NeedMore)? as usize;
+                    let index = distances.decode(|| reader.read(1))? as usize;
+                    if index >= 30 {
+                        return Err(Scan::Invalid);
+                    }
+                    let distance = DISTANCE_BASE[index] as usize + reader.read(DISTANCE_EXTRA[index] as u32).ok_or(Scan::NeedMore)? as usize;
+                    output.copy(distance, length)?;
+                }
+                _ => return Err(Scan::Invalid),
+            }
+        }
+    }
+}
+
+impl Carver for GzipCarver {
+    fn name(&self) -> &'static str {
+        "gzip"
+    }
+
+    fn extension(&self) -> &'static str {
+        "gz"
+    }
+
+    fn max_size(&self) -> usize {
+        512 * 1024 * 1024
+    }
+
+    fn find_header(&self, block: &[u8]) -> Option<usize> {
+        // Magic, deflate, no reserved flags, known extra flags and operating system
+        if block.len() >= 10 && block[..3] == [0x1F, 0x8B, 0x08] && block[3] & 0xE0 == 0
+            && [0, 2, 4].contains(&block[8]) && (block[9] <= 13 || block[9] == 255)
+        {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    fn find_end(&self, data: &[u8]) -> Scan {
+        let header_length = match GzipCarver::header_length(data) {
+            Ok(length) => length,
+            Err(scan) => return scan,
+        };
+        let mut reader = LsbBitReader::new(data, header_length);
+        let mut output = Output { window: vec![], checked: 0, crc: 0, size: 0 };
+        if let Err(scan) = GzipCarver::inflate(&mut reader, &mut output) {
+            return scan;
+        }
+
+        // The trailer has to match the decoded data
+        let trailer = reader.byte_position();
+        match (le_u32(data, trailer), le_u32(data, trailer + 4)) {
+            (Some(crc), Some(size)) if crc == output.crc && size == output.size as u32 => Scan::End(trailer + 8),
+            (Some(_), Some(_)) => Scan::Invalid,
+            _ => Scan::NeedMore,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::carve::crc32;
+
+    // Raw deflate streams from zlib
+    const STORED: [u8; 11] = [0x01, 0x06, 0x00, 0xF9, 0xFF, 0x73, 0x74, 0x6F, 0x72, 0x65, 0x64];
+    const FIXED: [u8; 7] = [0x4B, 0x4C, 0x4A, 0x4E, 0x84, 0x21, 0x00];
+    const DYNAMIC: [u8; 81] = [
+        0xA5, 0x8C, 0xCB, 0x11, 0x80, 0x20, 0x10, 0xC5, 0x5A, 0x79, 0x05, 0x38, 0xF6, 0x84, 0xB8, 0xA8,
+        0xFC, 0x96, 0xAF, 0x02, 0xD5, 0xEB, 0x78, 0xF3, 0xEC, 0x31, 0xC9, 0x4C, 0xCA, 0x4E, 0x88, 0xF5,
+        0x90, 0x06, 0x4B, 0xE2, 0xCB, 0x43, 0x71, 0x83, 0xAE, 0x2E, 0x64, 0xF0, 0x49, 0x09, 0xE5, 0xC9,
+        0x56, 0x8C, 0x8E, 0x95, 0xB7, 0xE9, 0x43, 0xC8, 0x96, 0x28, 0xE4, 0xF9, 0x95, 0xFF, 0x0E, 0xA3,
+        0xB7, 0xEB, 0xAC, 0x25, 0xA7, 0x18, 0xD8, 0x3B, 0x6B, 0xF4, 0xB1, 0x6F, 0x8A, 0x56, 0xB9, 0x88,
+        0x1B,
+    ];
+    // gzip -n of "hello gzip\n"
+    const MEMBER: [u8; 31] = [
+        0x1F, 0x8B, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xCB, 0x48, 0xCD, 0xC9, 0xC9, 0x57,
+        0x48, 0xAF, 0xCA, 0x2C, 0xE0, 0x02, 0x00, 0x39, 0x7C, 0x63, 0x56, 0x0B, 0x00, 0x00, 0x00,
+    ];
+
+    // Small streams stay completely in the window
+    fn inflate(stream: &[u8]) -> Vec<u8> {
+        let mut reader = LsbBitReader::new(stream, 0);
+        let mut output = Output { window: vec![], checked: 0, crc: 0, size: 0 };
+        assert!(GzipCarver::inflate(&mut reader, &mut output).is_ok());
+        assert_eq!(reader.byte_position(), stream.len());
+        assert_eq!(output.crc, crc32(&output.window));
+        assert_eq!(output.size, output.window.len() as u64);
+        output.window
+    }
+
+    #[test]
+    fn stored_block() {
+        assert_eq!(inflate(&STORED), b"stored");
+    }
+
+    #[test]
+    fn fixed_block() {
+        assert_eq!(inflate(&FIXED), b"abcabcabcabc");
+    }
+
+    #[test]
+    fn dynamic_block() {
+        let text = [&b"the quick brown fox jumps over the lazy dog, the lazy dog sleeps. ".repeat(2)[..], b"zyxwvutsrqponmlkjihgfedcba"].concat();
+        assert_eq!(inflate(&DYNAMIC), text);
+    }
+
+    #[test]
+    fn window_stays_small() {
+        let mut output = Output { window: vec![], checked: 0, crc: 0, size: 0 };
+        let data: Vec<u8> = (0..5 * WINDOW_SIZE).map(|i| (i % 251) as u8).collect();
+        data.iter().for_each(|&byte| output.push(byte));
+        assert!(output.window.len() <= 2 * WINDOW_SIZE);
+        assert!(output.copy(WINDOW_SIZE, 100).is_ok());
+        output.flush();
+        let mut expected = data.clone();
+        expected.extend_from_within(5 * WINDOW_SIZE - WINDOW_SIZE..5 * WINDOW_SIZE - WINDOW_SIZE + 100);
+        assert_eq!(output.crc, crc32(&expected));
+        assert_eq!(output.size, expected.len() as u64);
+    }
+
+    #[test]
+    fn member_end() {
+        assert!(matches!(GzipCarver.find_end(&MEMBER), Scan::End(31)));
+        // Followed by other data
+        let mut data = MEMBER.to_vec();
+        data.extend_from_slice(&[0xAA; 100]);
+        assert!(matches!(GzipCarver.find_end(&data), Scan::End(31)));
+        assert!(matches!(GzipCarver.find_end(&MEMBER[..25]), Scan::NeedMore));
+    }
+
+    #[test]
+    fn member_with_wrong_crc() {
+        let mut data = MEMBER;
+        data[23] ^= 1;
+        assert!(matches!(GzipCarver.find_end(&data), Scan::Invalid));
+    }
+}
@@ -0,0 +1,145 @@
+use super::{Carver, Scan};
+
+/// JPEG: SOI marker, a sequence of marker segments with a length field, entropy coded scan data after
+/// every SOS segment and the EOI marker at the end
+pub struct JpegCarver;
+
+// Markers, the byte after 0xFF
+const SOI: u8 = 0xD8;
+const EOI: u8 = 0xD9;
+const SOS: u8 = 0xDA;
+const DQT: u8 = 0xDB;
+const APP0: u8 = 0xE0;
+const APP1: u8 = 0xE1;
+const APP15: u8 = 0xEF;
+const TEM: u8 = 0x01;
+const RST0: u8 = 0xD0;
+const RST7: u8 = 0xD7;
+
+impl JpegCarver {
+    // Start of frame markers SOF0 - SOF15, C4 (DHT), C8 (JPG) and CC (DAC) are no frames
+    fn is_sof(marker: u8) -> bool {
+        (0xC0..=0xCF).contains(&marker) && marker != 0xC4 && marker != 0xC8 && marker != 0xCC
+    }
+
+    // Skips entropy coded data, returns the offset of the next marker.
+    // Inside the scan data 0xFF is stuffed as FF00, and restart markers RST0 - RST7 may appear.
+    fn skip_scan_data(data: &[u8], mut offset: usize) -> Option<usize> {
+        while offset + 1 < data.len() {
+            if data[offset] != 0xFF {
+                offset += 1;
+                continue;
+            }
+            match data[offset + 1] {
+                0x00 | RST0..=RST7 => offset += 2,
+                // Fill byte, the marker follows
+                0xFF => offset += 1,
+                _ => return Some(offset),
+            }
+        }
+        None
+    }
+
+    // Checks that every thumbnail started inside an APP segment (FFD8FF) is closed by an EOI inside the segment,
+    // otherwise the segment length doesn't fit the content
+    fn thumbnails_complete(payload: &[u8]) -> bool {
+        let mut depth = 0usize;
+        let mut offset = 0;
+        while offset + 1 < payload.len() {
+            if payload[offset] == 0xFF && payload[offset + 1] == SOI && payload.get(offset + 2) == Some(&0xFF) {
+                depth += 1;
+                offset += 2;
+            } else if payload[offset] == 0xFF && payload[offset + 1] == EOI {
+                depth = depth.saturating_sub(1);
+                offset += 2;
+            } else {
+                offset += 1;
+            }
+        }
+        depth == 0
+    }
+}
+
+impl Carver for JpegCarver {
+    fn name(&self) -> &'static str {
+        "JPEG"
+    }
+
+    fn extension(&self) -> &'static str {
+        "jpg"
+    }
+
+    fn max_size(&self) -> usize {
+        50 * 1024 * 1024
+    }
+
+    fn find_header(&self, block: &[u8]) -> Option<usize> {
+        // Files start at the beginning of a block: SOI, followed by APPn, DQT or SOF
+        if block.len() < 4 || block[0] != 0xFF || block[1] != SOI || block[2] != 0xFF {
+            return None;
+        }
+        let marker = block[3];
+        if (APP0..=APP15).contains(&marker) || marker == DQT || JpegCarver::is_sof(marker) {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    fn find_end(&self, data: &[u8]) -> Scan {
+        // Walk the marker segments behind SOI
+        let mut offset = 2;
+        loop {
+            if offset + 2 > data.len() {
+                return Scan::NeedMore;
+            }
+            if data[offset] != 0xFF {
+                return Scan::Invalid;
+            }
+            let marker = data[offset + 1];
+            match marker {
+                // Fill byte in front of a marker
+                0xFF => offset += 1,
+                EOI => return Scan::End(offset + 2),
+                // A second SOI outside of a segment is not part of this image
+                SOI | 0x00 => return Scan::Invalid,
+                // Markers without a length
+                TEM | RST0..=RST7 => offset += 2,
+                _ => {
+                    if offset + 4 > data.len() {
+                        return Scan::NeedMore;
+                    }
+                    // The length counts itself, but not the marker
+                    let length = u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize;
+                    if length < 2 {
+                        return Scan::Invalid;
+                    }
+                    let segment_end = offset + 2 + length;
+                    if segment_end > data.len() {
+                        return Scan::NeedMore;
+                    }
+
+                    // EXIF thumbnails are complete JPEGs inside APP1, their EOI is not the end of the image
+                    if marker == APP1 && !JpegCarver::thumbnails_complete(&data[offset + 4..segment_end]) {
+                        return Scan::Invalid;
+                    }
+
+                    offset = segment_end;
+                    if marker == SOS {
+                        // Scan data until the next marker
+                        match JpegCarver::skip_scan_data(data, offset) {
+                            Some(next_marker) => offset = next_marker,
+                            None => return Scan::NeedMore,
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn validate(&self, data: &[u8]) -> bool {
+        // A real image has a frame and at least one scan, a frame header is always followed by its length
+        data.windows(2).any(|w| w[0] == 0xFF && JpegCarver::is_sof(w[1]))
+            && data.windows(2).any(|w| w == [0xFF, SOS])
+    }
+}
@@ -0,0 +1,153 @@
+use super::{Carver, Scan};
+
+/// MP3: optional ID3v2 tag, a chain of MPEG audio frames and an optional ID3v1 tag.
+/// There is no length field, the file ends where the chain of frame headers breaks.
+pub struct Mp3Carver;
+
+// Bitrates in kbit/s by bitrate index: MPEG-1 layer I, II, III and MPEG-2/2.5 layer I, II and III
+const BITRATES: [[u32; 15]; 4] = [
+    [0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448],
+    [0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384],
+    [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320],
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+];
+const MPEG2_LAYER1_BITRATES: [u32; 15] = [0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256];
+
+// Sample rates of MPEG-1, the halves are MPEG-2, the quarters MPEG-2.5
+const SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+
+const ID3V2_HEADER_SIZE: usize = 10;
+const ID3V1_SIZE: usize = 128;
+
+// The parts of a frame header, that stay the same in a file
+#[derive(PartialEq)]
+struct Stream {
+    version: u8,
+    layer: u8,
+    sample_rate: u32,
+}
+
+impl Mp3Carver {
+    // Parses the frame header at offset, returns the stream and the length of the frame
+    fn frame(data: &[u8], offset: usize) -> Option<(Stream, usize)> {
+        let header = data.get(offset..offset + 4)?;
+        // 11 bits frame sync
+        if header[0] != 0xFF || header[1] & 0xE0 != 0xE0 {
+            return None;
+        }
+        // Version: 0 MPEG-2.5, 2 MPEG-2, 3 MPEG-1; layer: 1 layer III, 2 layer II, 3 layer I
+        let version = (header[1] >> 3) & 0x03;
+        let layer = (header[1] >> 1) & 0x03;
+        let bitrate_index = (header[2] >> 4) as usize;
+        let sample_rate_index = ((header[2] >> 2) & 0x03) as usize;
+        let padding = ((header[2] >> 1) & 0x01) as u32;
+        // Reserved values, free format bitrate, reserved emphasis
+        if version == 1 || layer == 0 || bitrate_index == 0 || bitrate_index == 15 || sample_rate_index == 3 || header[3] & 0x03 == 2 {
+            return None;
+        }
+
+        let bitrate = 1000 * match (version, layer) {
+            (3, layer) => BITRATES[(3 - layer) as usize][bitrate_index],
+            (_, 3) => MPEG2_LAYER1_BITRATES[bitrate_index],
+            _ => BITRATES[3][bitrate_index],
+        };
+        let sample_rate = match version {
+            3 => SAMPLE_RATES[sample_rate_index],
+            2 => SAMPLE_RATES[sample_rate_index] / 2,
+            _ => SAMPLE_RATES[sample_rate_index] / 4,
+        };
+        let length = match (version, layer) {
+            (_, 3) => (12 * bitrate / sample_rate + padding) * 4,
+            (3, _) | (_, 2) => 144 * bitrate / sample_rate + padding,
+            // MPEG-2/2.5 layer III has half the samples per frame
+            _ => 72 * bitrate / sample_rate + padding,
+        };
+        Some((Stream { version, layer, sample_rate }, length as usize))
+    }
+
+    // Size of an ID3v2 tag at the start of data, including header and footer
+    fn id3v2_size(data: &[u8]) -> Option<usize> {
+        if data.len() < ID3V2_HEADER_SIZE || !data.starts_with(b"ID3") || !(2..=4).contains(&data[3]) || data[4] == 0xFF {
+            return None;
+        }
+        // Synchsafe integer, 7 bits per byte
+        if data[6..10].iter().any(|&byte| byte & 0x80 != 0) {
+            return None;
+        }
+        let size = data[6..10].iter().fold(0, |size, &byte| (size << 7) | byte as usize);
+        let footer = if data[5] & 0x10 != 0 { 10 } else { 0 };
+        Some(ID3V2_HEADER_SIZE + size + footer)
+    }
+
+    // Walks the frames: the end of the last complete frame, the number of frames, and if something else
+    // than a frame follows them. Otherwise the data ended inside a frame.
+    fn frames_end(data: &[u8]) -> (usize, usize, bool) {
+        let mut offset = Mp3Carver::id3v2_size(data).unwrap_or(0);
+        let mut first_stream = None;
+        let mut frames = 0;
+        loop {
+            if offset + 4 > data.len() {
+                return (offset, frames, false);
+            }
+            match Mp3Carver::frame(data, offset) {
+                // All frames of a file have the same version, layer and sample rate
+                Some((stream, length)) if first_stream.as_ref().is_none_or(|first| *first == stream) => {
+                    if offset + length > data.len() {
+                        return (offset, frames, false);
+                    }
+                    first_stream.get_or_insert(stream);
+                    offset += length;
+                    frames += 1;
+                }
+                _ => return (offset, frames, true),
+            }
+        }
+    }
+}
+
+impl Carver for Mp3Carver {
+    fn name(&self) -> &'static str {
+        "MP3"
+    }
+
+    fn extension(&self) -> &'static str {
+        "mp3"
+    }
+
+    fn max_size(&self) -> usize {
+        200 * 1024 * 1024
+    }
+
+    fn find_header(&self, block: &[u8]) -> Option<usize> {
+        if Mp3Carver::id3v2_size(block).is_some() {
+            return Some(0);
+        }
+        // Without a tag a single frame sync is too easy to hit, the next frame has to follow
+        let (stream, length) = Mp3Carver::frame(block, 0)?;
+        match Mp3Carver::frame(block, length) {
+            Some((next, _)) if next == stream => Some(0),
+            _ => None,
+        }
+    }
+
+    fn find_end(&self, data: &[u8]) -> Scan {
+        let (offset, frames, complete) = Mp3Carver::frames_end(data);
+        if !complete {
+            return Scan::NeedMore;
+        }
+        if frames < 2 {
+            return Scan::Invalid;
+        }
+        // ID3v1 tag behind the last frame
+        if data[offset..].starts_with(b"TAG") {
+            return Scan::End(offset + ID3V1_SIZE);
+        }
+        Scan::End(offset)
+    }
+
+    fn find_end_at_eof(&self, data: &[u8]) -> Option<usize> {
+        // The frames up to the last complete one
+        let (offset, frames, _) = Mp3Carver::frames_end(data);
+        (frames >= 2).then_some(offset)
+    }
+}
@@ -0,0 +1,137 @@
+use super::{be_u32, be_u64, Carver, Scan};
+
+/// MP4, MOV and 3GP (ISO base media file format): a sequence of boxes with a 32 or 64 bit size,
+/// the first one is "ftyp" with the brand
+pub struct Mp4Carver;
+
+// Boxes that can be found at the top level of a file. Anything else after a box is not part of the file.
+const TOP_LEVEL_BOXES: [&[u8; 4]; 16] = [
+    b"ftyp", b"moov", b"mdat", b"free", b"skip", b"wide", b"uuid", b"meta",
+    b"pdin", b"moof", b"mfra", b"styp", b"sidx", b"emsg", b"prft", b"pnot",
+];
+
+impl Mp4Carver {
+    // Size of the box at offset: Ok(None) if this is not a box, Err(()) if its header is not in data yet.
+    // Ok(Some(0)) is a last box running up to the end of the file.
+    fn box_size(data: &[u8], offset: usize) -> Result<Option<usize>, ()> {
+        let (size, box_type) = match (be_u32(data, offset), data.get(offset + 4..offset + 8)) {
+            (Some(size), Some(box_type)) => (size, box_type),
+            _ => return Err(()),
+        };
+        // Another ftyp is the start of the next file
+        if !TOP_LEVEL_BOXES.iter().any(|known| &known[..] == box_type) || (offset > 0 && box_type == b"ftyp") {
+            return Ok(None);
+        }
+        match size {
+            // 64 bit size after the type, it can't be smaller than the header with it
+            1 => match be_u64(data, offset + 8) {
+                Some(size) if size >= 16 => Ok(Some(usize::try_from(size).unwrap_or(usize::MAX))),
+                Some(_) => Ok(None),
+                None => Err(()),
+            },
+            // Size 0 is "up to the end of the file", which we don't know
+            0 => Ok(Some(0)),
+            2..=7 => Ok(None),
+            size => Ok(Some(size as usize)),
+        }
+    }
+
+    // Types of all complete top level boxes
+    fn top_level_boxes(data: &[u8]) -> Vec<&[u8]> {
+        let mut boxes = vec![];
+        let mut offset = 0;
+        while let Ok(Some(size)) = Mp4Carver::box_size(data, offset) {
+            if size == 0 {
+                boxes.push(&data[offset + 4..offset + 8]);
+                break;
+            }
+            let end = match offset.checked_add(size) {
+                Some(end) if end <= data.len() => end,
+                _ => break,
+            };
+            boxes.push(&data[offset + 4..offset + 8]);
+            offset = end;
+        }
+        boxes
+    }
+}
+
+impl Carver for Mp4Carver {
+    fn name(&self) -> &'static str {
+        "MP4"
+    }
+
+    fn extension(&self) -> &'static str {
+        "mp4"
+    }
+
+    fn extension_for(&self, data: &[u8]) -> &'static str {
+        // The major brand in ftyp
+        match &data[8..12] {
+            b"qt  " => "mov",
+            brand if brand.starts_with(b"3gp") || brand.starts_with(b"3g2") => "3gp",
+            _ => "mp4",
+        }
+    }
+
+    fn max_size(&self) -> usize {
+        2 * 1024 * 1024 * 1024
+    }
+
+    fn find_header(&self, block: &[u8]) -> Option<usize> {
+        // ftyp is the first box: major brand, minor version, compatible brands of 4 bytes each
+        let size = be_u32(block, 0)?;
+        if &block.get(4..8)? == b"ftyp" && (16..=256).contains(&size) && size % 4 == 0
+            && block.get(8..12)?.iter().all(|byte| byte.is_ascii_alphanumeric() || *byte == b' ')
+        {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    fn find_end(&self, data: &[u8]) -> Scan {
+        let mut offset = 0;
+        loop {
+            match Mp4Carver::box_size(data, offset) {
+                // The last box takes all the data there is, up to the largest plausible file
+                Ok(Some(0)) if data.len() >= self.max_size() => {
+                    return Scan::MaybeEnd { end: self.max_size(), lookahead: self.max_size() };
+                }
+                Ok(Some(0)) => return Scan::NeedMore,
+                Ok(Some(size)) => {
+                    // A box running past the largest plausible file means this is garbage
+                    offset = match offset.checked_add(size) {
+                        Some(end) if end <= self.max_size() => end,
+                        _ => return Scan::Invalid,
+                    };
+                    // Read up to the header of the next box, a mdat box can be gigabytes.
+                    // Without a next box the file ends here.
+                    if offset + 8 > data.len() {
+                        return Scan::MaybeEnd { end: offset, lookahead: offset + 8 };
+                    }
+                }
+                // Not a top level box anymore, the file ends in front of it
+                Ok(None) => return Scan::End(offset),
+                Err(()) => return Scan::NeedMore,
+            }
+        }
+    }
+
+    fn find_end_at_eof(&self, data: &[u8]) -> Option<usize> {
+        // Only a box without a size can end with the free blocks, its length is a guess
+        let mut offset = 0;
+        while let Ok(Some(size)) = Mp4Carver::box_size(data, offset) {
+            if size == 0 {
+                return Some(data.len());
+            }
+            offset = offset.checked_add(size)?;
+        }
+        None
+    }
+
+    fn validate(&self, data: &[u8]) -> bool {
+        // Without the movie header the media data can't be played
+        Mp4Carver::top_level_boxes(data).contains(&&b"moov"[..])
+    }
+}
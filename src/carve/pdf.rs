@@ -0,0 +1,161 @@
+use super::{find_bytes, Carver, Scan};
+
+/// PDF: starts with "%PDF-". Incremental updates append objects, a new xref section and
+/// another "%%EOF", so the first "%%EOF" is not always the end of the document.
+pub struct PdfCarver;
+
+const EOF_MARKER: &[u8] = b"%%EOF";
+const PDF_WHITESPACE: &[u8] = b" \t\r\n\x0c";
+
+// What follows the end of a revision
+enum Continuation {
+    // Another revision of the same document
+    Update,
+    // Something else, the document ends here
+    Other,
+    // Not enough data to tell
+    Unknown,
+}
+
+impl PdfCarver {
+    // Parses a decimal number at the start of data, returns the number and its length
+    fn parse_number(data: &[u8]) -> Option<(usize, usize)> {
+        let length = data.iter().take_while(|byte| byte.is_ascii_digit()).count();
+        if length == 0 || length > 10 {
+            return None;
+        }
+        let number = std::str::from_utf8(&data[..length]).ok()?.parse().ok()?;
+        Some((number, length))
+    }
+
+    fn skip_whitespace(data: &[u8], mut offset: usize) -> usize {
+        while offset < data.len() && PDF_WHITESPACE.contains(&data[offset]) {
+            offset += 1;
+        }
+        offset
+    }
+
+    // Is there a cross-reference section at this offset: either a classic "xref" table,
+    // or an xref stream, which is an indirect object "12 0 obj"
+    fn is_xref_section(data: &[u8], offset: usize) -> bool {
+        match data.get(offset..) {
+            Some(rest) => rest.starts_with(b"xref") || PdfCarver::is_object_header(rest),
+            None => false,
+        }
+    }
+
+    // "<object number> <generation> obj"
+    fn is_object_header(data: &[u8]) -> bool {
+        let mut offset = 0;
+        for _ in 0..2 {
+            match PdfCarver::parse_number(&data[offset..]) {
+                Some((_, length)) => offset += length,
+                None => return false,
+            }
+            if data.get(offset) != Some(&b' ') {
+                return false;
+            }
+            offset += 1;
+        }
+        data[offset..].starts_with(b"obj")
+    }
+
+    // Checks the revision ending with the "%%EOF" at eof: "startxref", the offset of the
+    // cross-reference section, "%%EOF". The offset has to point to a xref section in data,
+    // as does the /Prev entry of the trailer, if there is one.
+    // Returns the end of the revision including the line break.
+    fn revision_end(data: &[u8], eof: usize) -> Option<usize> {
+        // startxref is right in front of %%EOF
+        let window_start = eof.saturating_sub(64);
+        let keyword = window_start + data[window_start..eof].windows(9).rposition(|w| w == b"startxref")?;
+        let number_start = PdfCarver::skip_whitespace(data, keyword + 9);
+        let (xref_offset, length) = PdfCarver::parse_number(&data[number_start..eof])?;
+        if PdfCarver::skip_whitespace(data, number_start + length) != eof {
+            return None;
+        }
+        if xref_offset >= keyword || !PdfCarver::is_xref_section(data, xref_offset) {
+            return None;
+        }
+
+        // The trailer (or the dictionary of the xref stream) points to the xref section of the previous revision
+        if let Some(prev) = find_bytes(&data[xref_offset..keyword], b"/Prev") {
+            let number_start = PdfCarver::skip_whitespace(data, xref_offset + prev + 5);
+            let (prev_offset, _) = PdfCarver::parse_number(&data[number_start..])?;
+            if !PdfCarver::is_xref_section(data, prev_offset) {
+                return None;
+            }
+        }
+
+        // The line break after %%EOF belongs to the file
+        let mut end = eof + EOF_MARKER.len();
+        if data[end..].starts_with(b"\r\n") {
+            end += 2;
+        } else if data[end..].starts_with(b"\n") || data[end..].starts_with(b"\r") {
+            end += 1;
+        }
+        Some(end)
+    }
+
+    // An incremental update starts with new objects or a xref table
+    fn continuation(data: &[u8], end: usize) -> Continuation {
+        let next = PdfCarver::skip_whitespace(data, end);
+        let rest = &data[next..];
+        if rest.len() < 16 {
+            Continuation::Unknown
+        } else if rest.starts_with(b"xref") || PdfCarver::is_object_header(rest) {
+            Continuation::Update
+        } else {
+            Continuation::Other
+        }
+    }
+}
+
+impl Carver for PdfCarver {
+    fn name(&self) -> &'static str {
+        "PDF"
+    }
+
+    fn extension(&self) -> &'static str {
+        "pdf"
+    }
+
+    fn max_size(&self) -> usize {
+        200 * 1024 * 1024
+    }
+
+    fn find_header(&self, block: &[u8]) -> Option<usize> {
+        // "%PDF-1.7", "%PDF-2.0"
+        if block.len() >= 8 && block.starts_with(b"%PDF-") && block[5].is_ascii_digit() && block[6] == b'.' && block[7].is_ascii_digit() {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    fn find_end(&self, data: &[u8]) -> Scan {
+        let mut search = 0;
+        // Every %%EOF, only those with a valid startxref end a revision. Markers inside of
+        // stream data or broken revisions are skipped.
+        while let Some(found) = find_bytes(&data[search..], EOF_MARKER) {
+            let eof = search + found;
+            search = eof + EOF_MARKER.len();
+
+            let end = match PdfCarver::revision_end(data, eof) {
+                Some(end) => end,
+                None => continue,
+            };
+            match PdfCarver::continuation(data, end) {
+                Continuation::Update => search = end,
+                Continuation::Other => return Scan::End(end),
+                // Without more free blocks the document ends here
+                Continuation::Unknown => return Scan::MaybeEnd { end, lookahead: PdfCarver::skip_whitespace(data, end) + 16 },
+            }
+        }
+        Scan::NeedMore
+    }
+
+    fn validate(&self, data: &[u8]) -> bool {
+        // Every document has a catalog, referenced by the trailer
+        find_bytes(data, b"/Root").is_some()
+    }
+}
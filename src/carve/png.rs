@@ -0,0 +1,66 @@
+use super::{crc32, Carver, Scan};
+
+/// PNG: 8 byte signature, followed by length-prefixed chunks from IHDR to IEND
+pub struct PngCarver;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+impl Carver for PngCarver {
+    fn name(&self) -> &'static str {
+        "PNG"
+    }
+
+    fn extension(&self) -> &'static str {
+        "png"
+    }
+
+    fn max_size(&self) -> usize {
+        100 * 1024 * 1024
+    }
+
+    fn find_header(&self, block: &[u8]) -> Option<usize> {
+        // Files start at the beginning of a block, the first chunk is always IHDR with 13 bytes
+        if block.len() >= 16 && block[..8] == SIGNATURE && block[8..16] == [0, 0, 0, 13, b'I', b'H', b'D', b'R'] {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    fn find_end(&self, data: &[u8]) -> Scan {
+        // Walk the chunks: length (4 bytes, big endian), type (4 bytes), data, CRC-32 over type and data
+        let mut offset = SIGNATURE.len();
+        loop {
+            if offset + 8 > data.len() {
+                return Scan::NeedMore;
+            }
+            let length = u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+            let chunk_type = &data[offset + 4..offset + 8];
+
+            // The length is limited to 2^31 - 1, chunk types consist of ASCII letters
+            if length > i32::MAX as usize || !chunk_type.iter().all(|b| b.is_ascii_alphabetic()) {
+                return Scan::Invalid;
+            }
+
+            let chunk_end = offset + 12 + length;
+            if chunk_end > data.len() {
+                return Scan::NeedMore;
+            }
+            let crc = u32::from_be_bytes(data[chunk_end - 4..chunk_end].try_into().unwrap());
+            if crc32(&data[offset + 4..chunk_end - 4]) != crc {
+                return Scan::Invalid;
+            }
+
+            // The file ends exactly behind the IEND chunk
+            if chunk_type == b"IEND" {
+                return Scan::End(chunk_end);
+            }
+            offset = chunk_end;
+        }
+    }
+
+    fn validate(&self, data: &[u8]) -> bool {
+        // An image needs image data, find_end already checked all chunks
+        data.windows(4).any(|w| w == b"IDAT")
+    }
+}
@@ -0,0 +1,119 @@
+use super::{le_u16, le_u32, Carver, Scan};
+
+/// RIFF containers: "RIFF", the size of the rest of the file and the form type,
+/// followed by chunks. WAV, AVI and WebP are RIFF files.
+pub struct RiffCarver;
+
+// Form types and the extension of the files
+const FORMS: [(&[u8], &str); 3] = [(b"WAVE", "wav"), (b"AVI ", "avi"), (b"WEBP", "webp")];
+
+impl RiffCarver {
+    // Checks the first chunk of the form, it describes the content of the file.
+    // data is the first block, the fmt chunk of WAVE has to be in it.
+    fn first_chunk_valid(data: &[u8]) -> bool {
+        let (id, size) = match (data.get(12..16), le_u32(data, 16)) {
+            (Some(id), Some(size)) => (id, size as usize),
+            _ => return false,
+        };
+        match &data[8..12] {
+            // Format of the samples: at least 16 bytes, channels, sample rate and block align can't be 0.
+            // Chunks like bext, JUNK or LIST can come first.
+            b"WAVE" => {
+                let mut offset = 12;
+                while let Some(id) = data.get(offset..offset + 4).filter(|id| *id != b"fmt ") {
+                    let size = match le_u32(data, offset + 4) {
+                        Some(size) if id.iter().all(|byte| (0x20..0x7F).contains(byte)) => size as usize,
+                        _ => return false,
+                    };
+                    offset = match offset.checked_add(8 + size + size % 2) {
+                        Some(next) => next,
+                        None => return false,
+                    };
+                }
+                le_u32(data, offset + 4).is_some_and(|size| size >= 16)
+                    && le_u16(data, offset + 8).is_some_and(|format| format != 0)
+                    && le_u16(data, offset + 10).is_some_and(|channels| channels != 0)
+                    && le_u32(data, offset + 12).is_some_and(|sample_rate| sample_rate != 0)
+                    && le_u16(data, offset + 20).is_some_and(|block_align| block_align != 0)
+            }
+            // The header list with the main AVI header
+            b"AVI " => id == b"LIST" && data.get(20..24) == Some(b"hdrl"),
+            // Lossy with the start code of a VP8 key frame, lossless with its signature, or extended
+            b"WEBP" => match id {
+                b"VP8 " => data.get(23..26) == Some(&[0x9D, 0x01, 0x2A]),
+                b"VP8L" => data.get(20) == Some(&0x2F),
+                b"VP8X" => size == 10,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    // End of the RIFF chunk at offset, the size doesn't include the 8 byte header
+    fn riff_end(data: &[u8], offset: usize) -> Option<usize> {
+        Some(offset + 8 + le_u32(data, offset + 4)? as usize)
+    }
+}
+
+impl Carver for RiffCarver {
+    fn name(&self) -> &'static str {
+        "RIFF"
+    }
+
+    fn extension(&self) -> &'static str {
+        "riff"
+    }
+
+    fn extension_for(&self, data: &[u8]) -> &'static str {
+        FORMS.iter().find(|(form, _)| &data[8..12] == *form).map_or(self.extension(), |(_, extension)| extension)
+    }
+
+    fn max_size(&self) -> usize {
+        2 * 1024 * 1024 * 1024
+    }
+
+    fn find_header(&self, block: &[u8]) -> Option<usize> {
+        let form = block.get(8..12)?;
+        if block.starts_with(b"RIFF") && FORMS.iter().any(|(known, _)| *known == form) && RiffCarver::first_chunk_valid(block) {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    fn find_end(&self, data: &[u8]) -> Scan {
+        let mut end = match RiffCarver::riff_end(data, 0) {
+            Some(end) => end,
+            None => return Scan::Invalid,
+        };
+        // AVI files over 1 GB continue with "AVIX" RIFF chunks
+        if &data[8..12] == b"AVI " {
+            loop {
+                if end + 12 > data.len() {
+                    return Scan::MaybeEnd { end, lookahead: end + 12 };
+                }
+                if !data[end..].starts_with(b"RIFF") || &data[end + 8..end + 12] != b"AVIX" {
+                    break;
+                }
+                end = RiffCarver::riff_end(data, end).unwrap();
+            }
+        }
+        Scan::End(end)
+    }
+
+    fn validate(&self, data: &[u8]) -> bool {
+        // The chunks have to fill the RIFF chunk exactly, their IDs are readable text.
+        // Chunks are padded to an even size.
+        let riff_end = RiffCarver::riff_end(data, 0).unwrap_or(0).min(data.len());
+        let mut offset = 12;
+        while offset + 8 <= riff_end {
+            if !data[offset..offset + 4].iter().all(|byte| (0x20..0x7F).contains(byte)) {
+                return false;
+            }
+            let size = le_u32(data, offset + 4).unwrap() as usize;
+            offset += 8 + size + size % 2;
+        }
+        // The last chunk may lack its padding byte
+        offset == riff_end || offset == riff_end + 1
+    }
+}
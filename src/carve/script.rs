@@ -0,0 +1,79 @@
+use super::text::{text_end, text_end_at_eof, Encoding};
+use super::{Carver, Scan};
+
+/// Scripts starting with a shebang line: "#!/bin/sh", "#!/usr/bin/env python3", ...
+/// The script ends with the text, at the first zero byte or the first block that isn't text.
+pub struct ScriptCarver {
+    block_size: usize,
+}
+
+const INTERPRETERS: [&str; 13] = ["sh", "bash", "dash", "zsh", "ksh", "csh", "tcsh", "python", "perl", "ruby", "php", "node", "awk"];
+
+impl ScriptCarver {
+    /// The file system block size is the step, in which the script is checked for text
+    pub fn new(block_size: usize) -> Self {
+        ScriptCarver { block_size }
+    }
+
+    // Name of the interpreter without version, "python3.11" is "python"
+    fn interpreter(line: &str) -> Option<&str> {
+        let mut words = line.split_whitespace();
+        let mut program = words.next()?.rsplit('/').next()?;
+        if program == "env" {
+            program = words.find(|word| !word.starts_with('-'))?;
+        }
+        Some(program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.'))
+    }
+}
+
+impl Carver for ScriptCarver {
+    fn name(&self) -> &'static str {
+        "Script"
+    }
+
+    fn extension(&self) -> &'static str {
+        "sh"
+    }
+
+    fn extension_for(&self, data: &[u8]) -> &'static str {
+        let line = data.split(|&byte| byte == b'\n').next().unwrap_or_default();
+        match ScriptCarver::interpreter(&String::from_utf8_lossy(&line[2..])) {
+            Some("python") => "py",
+            Some("perl") => "pl",
+            Some("ruby") => "rb",
+            Some("php") => "php",
+            Some("node") => "js",
+            Some("awk") => "awk",
+            _ => self.extension(),
+        }
+    }
+
+    fn max_size(&self) -> usize {
+        10 * 1024 * 1024
+    }
+
+    fn find_header(&self, block: &[u8]) -> Option<usize> {
+        // "#!", an absolute path to a known interpreter, all on a short first line
+        let line_end = block.iter().take(256).position(|&byte| byte == b'\n')?;
+        let line = std::str::from_utf8(block.get(2..line_end)?).ok()?;
+        if !block.starts_with(b"#!") || !line.trim_start().starts_with('/') {
+            return None;
+        }
+        match ScriptCarver::interpreter(line) {
+            Some(interpreter) if INTERPRETERS.contains(&interpreter) => Some(0),
+            _ => None,
+        }
+    }
+
+    fn starts_at_block(&self) -> bool {
+        true
+    }
+
+    fn find_end(&self, data: &[u8]) -> Scan {
+        text_end(data, Encoding::Utf8, self.block_size)
+    }
+
+    fn find_end_at_eof(&self, data: &[u8]) -> Option<usize> {
+        text_end_at_eof(data, Encoding::Utf8, self.block_size)
+    }
+}
@@ -0,0 +1,70 @@
+use super::{crc32, le_u32, le_u64, Carver, Scan};
+
+/// 7z archive: 32 byte signature header with offset, size and CRC-32 of the header at the end of the archive
+pub struct SevenZipCarver;
+
+const SIGNATURE: &[u8] = b"7z\xBC\xAF\x27\x1C";
+const SIGNATURE_HEADER_SIZE: usize = 32;
+
+// Property IDs the next header starts with
+const HEADER: u8 = 0x01;
+const ENCODED_HEADER: u8 = 0x17;
+
+impl SevenZipCarver {
+    // Offset and size of the next header, if the CRC-32 of the start header matches
+    fn next_header(data: &[u8]) -> Option<(usize, usize)> {
+        if le_u32(data, 8)? != crc32(data.get(12..SIGNATURE_HEADER_SIZE)?) {
+            return None;
+        }
+        let offset = SIGNATURE_HEADER_SIZE.checked_add(le_u64(data, 12)? as usize)?;
+        let size = le_u64(data, 20)? as usize;
+        if size == 0 {
+            return None;
+        }
+        Some((offset, size))
+    }
+}
+
+impl Carver for SevenZipCarver {
+    fn name(&self) -> &'static str {
+        "7z"
+    }
+
+    fn extension(&self) -> &'static str {
+        "7z"
+    }
+
+    fn max_size(&self) -> usize {
+        512 * 1024 * 1024
+    }
+
+    fn find_header(&self, block: &[u8]) -> Option<usize> {
+        // Signature, major version 0
+        if block.starts_with(SIGNATURE) && block.get(6) == Some(&0) && SevenZipCarver::next_header(block).is_some() {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    fn find_end(&self, data: &[u8]) -> Scan {
+        match SevenZipCarver::next_header(data) {
+            Some((offset, size)) => match offset.checked_add(size) {
+                Some(end) if end <= self.max_size() => Scan::End(end),
+                _ => Scan::Invalid,
+            },
+            None => Scan::Invalid,
+        }
+    }
+
+    fn validate(&self, data: &[u8]) -> bool {
+        // The next header is at the end of the archive and has its own CRC-32
+        match SevenZipCarver::next_header(data) {
+            Some((offset, _)) => {
+                let next_header = &data[offset..];
+                [HEADER, ENCODED_HEADER].contains(&next_header[0]) && le_u32(data, 28) == Some(crc32(next_header))
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use super::{be_u16, be_u32, Carver, Scan};
+
+/// SQLite 3 database: 100 byte header with page size and page count, followed by the pages
+pub struct SqliteCarver;
+
+/// Single table leaf pages of SQLite databases, which are left in unused blocks
+/// when the rest of the database, including its header, is overwritten
+pub struct SqlitePageCarver;
+
+const MAGIC: &[u8] = b"SQLite format 3\0";
+const HEADER_SIZE: usize = 100;
+
+// B-tree page types
+const INTERIOR_INDEX: u8 = 0x02;
+const INTERIOR_TABLE: u8 = 0x05;
+const LEAF_INDEX: u8 = 0x0A;
+const LEAF_TABLE: u8 = 0x0D;
+
+// Page size and number of pages from the database header
+fn database_size(data: &[u8]) -> Option<(usize, usize)> {
+    let page_size = match be_u16(data, 16)? {
+        1 => 65536,
+        size => size as usize,
+    };
+    if !page_size.is_power_of_two() || page_size < 512 {
+        return None;
+    }
+    // Fixed payload fractions
+    if data.get(21..24)? != [64, 32, 32] {
+        return None;
+    }
+    // The page count is only valid, if the change counter matches "version valid for".
+    // Very old versions don't update it, we can't tell the size of these files.
+    let page_count = be_u32(data, 28)? as usize;
+    if page_count == 0 || be_u32(data, 24)? != be_u32(data, 92)? {
+        return None;
+    }
+    Some((page_size, page_count))
+}
+
+// Checks the b-tree page header at header_offset of a page with usable_size bytes: the cell pointer
+// array is in front of the cell content area, all cells and free blocks are inside of it
+fn is_btree_page(page: &[u8], header_offset: usize, usable_size: usize) -> bool {
+    let page_type = match page.get(header_offset) {
+        Some(&page_type) => page_type,
+        None => return false,
+    };
+    let header_size = match page_type {
+        LEAF_INDEX | LEAF_TABLE => 8,
+        INTERIOR_INDEX | INTERIOR_TABLE => 12,
+        _ => return false,
+    };
+    let (first_freeblock, cell_count, content_start) = match (be_u16(page, header_offset + 1), be_u16(page, header_offset + 3), be_u16(page, header_offset + 5)) {
+        (Some(freeblock), Some(cells), Some(content)) => (freeblock as usize, cells as usize, content as usize),
+        _ => return false,
+    };
+    // 0 stands for 65536
+    let content_start = if content_start == 0 { 65536 } else { content_start };
+    let pointers_end = header_offset + header_size + 2 * cell_count;
+    if pointers_end > content_start || content_start > usable_size || page.get(header_offset + 7).is_none_or(|&fragmented| fragmented > 60) {
+        return false;
+    }
+    if first_freeblock != 0 && (first_freeblock < content_start || first_freeblock + 4 > usable_size) {
+        return false;
+    }
+    (0..cell_count).all(|cell| match be_u16(page, header_offset + header_size + 2 * cell) {
+        Some(pointer) => (content_start..usable_size).contains(&(pointer as usize)),
+        None => false,
+    })
+}
+
+// SQLite variable length integer: up to 9 bytes, returns the value and its length
+fn varint(data: &[u8], offset: usize) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+    for i in 0..9 {
+        let byte = *data.get(offset + i)?;
+        if i == 8 {
+            return Some(((value << 8) | byte as u64, 9));
+        }
+        value = (value << 7) | (byte & 0x7F) as u64;
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+impl SqlitePageCarver {
+    // The page size isn't stored in the page. The cell content area grows from the end of the page,
+    // so the smallest page size holding all cell pointers is the page size.
+    fn page_size(block: &[u8]) -> Option<usize> {
+        if block.first() != Some(&LEAF_TABLE) {
+            return None;
+        }
+        let cell_count = be_u16(block, 3)? as usize;
+        if cell_count == 0 {
+            return None;
+        }
+        let mut highest = be_u16(block, 5)? as usize;
+        for cell in 0..cell_count {
+            highest = highest.max(be_u16(block, 8 + 2 * cell)? as usize);
+        }
+        let page_size = (highest + 1).next_power_of_two().max(512);
+        if page_size > 65536 || !is_btree_page(block, 0, page_size) {
+            return None;
+        }
+        Some(page_size)
+    }
+
+    // Every cell of a table leaf: payload size, row id, payload, and an overflow page number
+    // if the payload doesn't fit into the page
+    fn cells_fit(page: &[u8]) -> bool {
+        let usable_size = page.len();
+        let cell_count = be_u16(page, 3).unwrap_or(0) as usize;
+        (0..cell_count).all(|cell| {
+            let pointer = be_u16(page, 8 + 2 * cell).unwrap_or(0) as usize;
+            let (payload, payload_length) = match varint(page, pointer) {
+                Some(payload) => payload,
+                None => return false,
+            };
+            let row_id_length = match varint(page, pointer + payload_length) {
+                Some((_, length)) => length,
+                None => return false,
+            };
+            // Payload stored in the page, see "Cell Payload Overflow Pages" in the file format
+            let max_local = (usable_size - 35) as u64;
+            let local = if payload <= max_local {
+                payload
+            } else {
+                let min_local = ((usable_size - 12) * 32 / 255 - 23) as u64;
+                let k = min_local + (payload - min_local) % (usable_size as u64 - 4);
+                (if k <= max_local { k } else { min_local }) + 4
+            };
+            pointer + payload_length + row_id_length + local as usize <= usable_size
+        })
+    }
+}
+
+impl Carver for SqliteCarver {
+    fn name(&self) -> &'static str {
+        "SQLite"
+    }
+
+    fn extension(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn max_size(&self) -> usize {
+        1024 * 1024 * 1024
+    }
+
+    fn find_header(&self, block: &[u8]) -> Option<usize> {
+        // The first page holds the schema table, its b-tree header follows the database header
+        let (page_size, _) = database_size(block)?;
+        if block.starts_with(MAGIC) && is_btree_page(block, HEADER_SIZE, page_size) {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    fn find_end(&self, data: &[u8]) -> Scan {
+        match database_size(data) {
+            Some((page_size, page_count)) => match page_size.checked_mul(page_count) {
+                Some(size) if size <= self.max_size() => Scan::End(size),
+                _ => Scan::Invalid,
+            },
+            None => Scan::Invalid,
+        }
+    }
+
+    fn validate(&self, data: &[u8]) -> bool {
+        let (page_size, _) = match database_size(data) {
+            Some(size) => size,
+            None => return false,
+        };
+        // Reserved space at the end of every page, e.g. for encryption
+        let usable_size = page_size - data[20] as usize;
+        // Pointer map pages of auto-vacuum databases start with the same bytes as b-tree pages
+        let auto_vacuum = be_u32(data, 52).unwrap_or(0) != 0;
+
+        // Overflow and free list pages start with a page number, 0 for all but huge databases.
+        // A page starting with a b-tree page type has to be a valid b-tree page.
+        data.chunks(page_size).enumerate().all(|(number, page)| {
+            let header_offset = if number == 0 { HEADER_SIZE } else { 0 };
+            match page[header_offset] {
+                INTERIOR_INDEX | INTERIOR_TABLE if auto_vacuum => true,
+                INTERIOR_INDEX | INTERIOR_TABLE | LEAF_INDEX | LEAF_TABLE => is_btree_page(page, header_offset, usable_size),
+                _ => number != 0,
+            }
+        })
+    }
+}
+
+impl Carver for SqlitePageCarver {
+    fn name(&self) -> &'static str {
+        "SQLite page"
+    }
+
+    fn extension(&self) -> &'static str {
+        "sqlite-page"
+    }
+
+    fn max_size(&self) -> usize {
+        65536
+    }
+
+    fn find_header(&self, block: &[u8]) -> Option<usize> {
+        SqlitePageCarver::page_size(block).map(|_| 0)
+    }
+
+    fn find_end(&self, data: &[u8]) -> Scan {
+        match SqlitePageCarver::page_size(data) {
+            Some(page_size) => Scan::End(page_size),
+            None => Scan::Invalid,
+        }
+    }
+
+    fn validate(&self, data: &[u8]) -> bool {
+        SqlitePageCarver::cells_fit(data)
+    }
+}
@@ -0,0 +1,128 @@
+use super::{Carver, Scan};
+
+/// tar archive (ustar and GNU): 512 byte headers, each followed by the file data padded to
+/// 512 bytes. The archive ends with two zero blocks.
+pub struct TarCarver;
+
+const BLOCK_SIZE: usize = 512;
+// GNU tar fills the last record of 20 blocks with zeros
+const RECORD_SIZE: usize = 20 * BLOCK_SIZE;
+
+impl TarCarver {
+    // Octal number, terminated by a space or zero byte. GNU tar stores big numbers in base 256
+    // with the highest bit of the first byte set, the rest of that byte is the start of the number.
+    // Negative numbers (0x40 set too) are no sizes.
+    fn number(field: &[u8]) -> Option<u64> {
+        if field[0] & 0x80 != 0 {
+            if field[0] & 0x40 != 0 {
+                return None;
+            }
+            return field[1..].iter().try_fold((field[0] & 0x7F) as u64, |number, &byte| Some(number.checked_mul(256)? | byte as u64));
+        }
+        let digits = field.iter().skip_while(|&&byte| byte == b' ').take_while(|&&byte| byte != 0 && byte != b' ');
+        let mut number = 0u64;
+        for &digit in digits {
+            if !(b'0'..=b'7').contains(&digit) {
+                return None;
+            }
+            number = number.checked_mul(8)? + (digit - b'0') as u64;
+        }
+        Some(number)
+    }
+
+    // The checksum is the sum of all header bytes, with the checksum field counted as spaces
+    fn is_header(block: &[u8]) -> bool {
+        let magic = &block[257..265];
+        if magic != b"ustar\x0000" && magic != b"ustar  \0" {
+            return false;
+        }
+        let sum: u64 = block.iter().enumerate()
+            .map(|(i, &byte)| if (148..156).contains(&i) { b' ' as u64 } else { byte as u64 })
+            .sum();
+        TarCarver::number(&block[148..156]) == Some(sum)
+    }
+}
+
+impl Carver for TarCarver {
+    fn name(&self) -> &'static str {
+        "tar"
+    }
+
+    fn extension(&self) -> &'static str {
+        "tar"
+    }
+
+    fn max_size(&self) -> usize {
+        1024 * 1024 * 1024
+    }
+
+    fn find_header(&self, block: &[u8]) -> Option<usize> {
+        if block.len() >= BLOCK_SIZE && TarCarver::is_header(&block[..BLOCK_SIZE]) {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    fn find_end(&self, data: &[u8]) -> Scan {
+        let mut offset = 0;
+        loop {
+            let header = match data.get(offset..offset + BLOCK_SIZE) {
+                Some(header) => header,
+                None => return Scan::NeedMore,
+            };
+            if header.iter().all(|&byte| byte == 0) {
+                break;
+            }
+            if !TarCarver::is_header(header) {
+                return Scan::Invalid;
+            }
+            // The data is padded to whole blocks
+            let next = TarCarver::number(&header[124..136])
+                .and_then(|size| usize::try_from(size).ok())
+                .and_then(|size| size.div_ceil(BLOCK_SIZE).checked_mul(BLOCK_SIZE))
+                .and_then(|size| size.checked_add(offset + BLOCK_SIZE));
+            offset = match next {
+                Some(next) if next <= self.max_size() => next,
+                _ => return Scan::Invalid,
+            };
+        }
+
+        // Two zero blocks end the archive
+        let end = offset + 2 * BLOCK_SIZE;
+        match data.get(offset + BLOCK_SIZE..end) {
+            Some(block) if block.iter().all(|&byte| byte == 0) => (),
+            Some(_) => return Scan::Invalid,
+            None => return Scan::End(end),
+        }
+        // And the zeros up to the end of the record, if they are there
+        let record_end = end.div_ceil(RECORD_SIZE) * RECORD_SIZE;
+        match data.get(end..record_end.min(data.len())) {
+            Some(rest) if rest.iter().any(|&byte| byte != 0) => Scan::End(end),
+            _ if data.len() >= record_end => Scan::End(record_end),
+            _ => Scan::MaybeEnd { end, lookahead: record_end },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn octal_number() {
+        assert_eq!(TarCarver::number(b"00000001750\0"), Some(1000));
+        assert_eq!(TarCarver::number(b"   1750 \0\0\0\0"), Some(1000));
+        assert_eq!(TarCarver::number(b"0000000017a\0"), None);
+    }
+
+    #[test]
+    fn base_256_number() {
+        assert_eq!(TarCarver::number(&[0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x03, 0xE8]), Some(1000));
+        // The low bits of the first byte belong to the number
+        assert_eq!(TarCarver::number(&[0x81, 0, 0, 0, 0, 0, 0, 0x01]), Some((1 << 56) + 1));
+        assert_eq!(TarCarver::number(&[0x81, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]), None);
+        // Negative
+        assert_eq!(TarCarver::number(&[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFC, 0x18]), None);
+    }
+}
@@ -0,0 +1,269 @@
+use super::{Carver, Scan};
+
+/// Plain text: runs of free blocks with text in the same encoding. The format is guessed from the content.
+/// Text has no length or footer, so every block is classified on its own.
+pub struct TextCarver {
+    block_size: usize,
+}
+
+// Shorter text is most likely a leftover in a binary file
+const MIN_TEXT_LENGTH: usize = 16;
+
+/// Encoding of text data
+#[derive(Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Ascii,
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Encoding {
+    // ASCII is part of UTF-8, a file can have both kinds of blocks
+    fn is_compatible(self, other: Encoding) -> bool {
+        let is_8_bit = |encoding| matches!(encoding, Encoding::Ascii | Encoding::Utf8);
+        self == other || (is_8_bit(self) && is_8_bit(other))
+    }
+
+    // Size of a code unit, the end of text is a zero unit
+    fn unit_size(self) -> usize {
+        match self {
+            Encoding::Ascii | Encoding::Utf8 => 1,
+            Encoding::Utf16Le | Encoding::Utf16Be => 2,
+        }
+    }
+}
+
+// Text is almost only printable characters, and has no zero characters
+fn is_printable(characters: impl Iterator<Item = char>) -> bool {
+    let (mut printable, mut total) = (0, 0);
+    for character in characters {
+        if character == '\0' {
+            return false;
+        }
+        if !character.is_control() || matches!(character, '\t' | '\n' | '\r' | '\x0c') {
+            printable += 1;
+        }
+        total += 1;
+    }
+    total > 0 && printable * 100 >= total * 95
+}
+
+// Without zero bytes at the end, they are the unused rest of the last block of a file
+fn trim_zeros(data: &[u8], unit_size: usize) -> &[u8] {
+    let mut length = data.len() - data.len() % unit_size;
+    while length >= unit_size && data[length - unit_size..length].iter().all(|&byte| byte == 0) {
+        length -= unit_size;
+    }
+    &data[..length]
+}
+
+fn utf8_encoding(data: &[u8]) -> Option<Encoding> {
+    let data = trim_zeros(data, 1);
+    // A character can be split between two blocks, skip its parts at the start and the end
+    let start = data.iter().take(3).take_while(|&&byte| (0x80..0xC0).contains(&byte)).count();
+    let text = match std::str::from_utf8(&data[start..]) {
+        Ok(text) => text,
+        Err(error) if error.error_len().is_none() => std::str::from_utf8(&data[start..start + error.valid_up_to()]).unwrap(),
+        Err(_) => return None,
+    };
+    if !is_printable(text.chars()) {
+        return None;
+    }
+    Some(if text.is_ascii() { Encoding::Ascii } else { Encoding::Utf8 })
+}
+
+fn utf16_encoding(data: &[u8]) -> Option<Encoding> {
+    let data = trim_zeros(data, 2);
+    // Latin text in UTF-16 has a zero byte in most characters, the high byte
+    let pairs = data.len() / 2;
+    let zero_low_bytes = data.chunks(2).filter(|pair| pair[0] == 0).count();
+    let zero_high_bytes = data.chunks(2).filter(|pair| pair[1] == 0).count();
+    let (encoding, decode): (Encoding, fn([u8; 2]) -> u16) = if zero_high_bytes * 3 >= pairs && zero_low_bytes * 20 < pairs {
+        (Encoding::Utf16Le, u16::from_le_bytes)
+    } else if zero_low_bytes * 3 >= pairs && zero_high_bytes * 20 < pairs {
+        (Encoding::Utf16Be, u16::from_be_bytes)
+    } else {
+        return None;
+    };
+
+    let units: Vec<u16> = data.chunks(2).map(|pair| decode([pair[0], pair[1]])).collect();
+    // Surrogate pairs can be split between two blocks
+    let start = units.first().is_some_and(|unit| (0xDC00..0xE000).contains(unit)) as usize;
+    let end = units.len() - units.last().is_some_and(|unit| (0xD800..0xDC00).contains(unit)) as usize;
+    let characters: Option<Vec<char>> = char::decode_utf16(units[start..end.max(start)].iter().copied()).map(Result::ok).collect();
+    if !is_printable(characters?.into_iter()) {
+        return None;
+    }
+    Some(encoding)
+}
+
+/// Encoding of the text in data, None if it isn't text
+pub fn text_encoding(data: &[u8]) -> Option<Encoding> {
+    utf8_encoding(data).or_else(|| utf16_encoding(data))
+}
+
+/// End of text in the encoding: the first zero character, or the first piece of `step` bytes
+/// (a file system block) with something else than text in the encoding
+pub fn text_end(data: &[u8], encoding: Encoding, step: usize) -> Scan {
+    let unit_size = encoding.unit_size();
+    for start in (0..data.len()).step_by(step) {
+        let piece = match data.get(start..start + step) {
+            Some(piece) => piece,
+            None => return Scan::NeedMore,
+        };
+        let text_length = piece.chunks(unit_size).position(|unit| unit.iter().all(|&byte| byte == 0))
+            .map_or(piece.len(), |unit| unit * unit_size);
+        // Binary data after the last piece with text
+        if text_length > 0 && !text_encoding(&piece[..text_length]).is_some_and(|found| found.is_compatible(encoding)) {
+            return if start > 0 { Scan::End(start) } else { Scan::Invalid };
+        }
+        // The rest of the last block is filled with zeros
+        if text_length < piece.len() {
+            return Scan::End(start + text_length);
+        }
+    }
+    Scan::NeedMore
+}
+
+/// End of text, when no more data comes: the end found so far, or all the complete pieces of text
+pub fn text_end_at_eof(data: &[u8], encoding: Encoding, step: usize) -> Option<usize> {
+    match text_end(data, encoding, step) {
+        Scan::End(end) => Some(end),
+        Scan::NeedMore => Some(data.len() - data.len() % step).filter(|&end| end > 0),
+        Scan::Invalid | Scan::MaybeEnd { .. } => None,
+    }
+}
+
+impl TextCarver {
+    /// The file system block size, text is classified block by block
+    pub fn new(block_size: usize) -> Self {
+        TextCarver { block_size }
+    }
+
+    // The start of the text, decoded
+    fn decode(&self, data: &[u8]) -> String {
+        let start = &data[..data.len().min(64 * 1024)];
+        match text_encoding(&start[..start.len().min(self.block_size)]) {
+            Some(encoding @ (Encoding::Utf16Le | Encoding::Utf16Be)) => {
+                let decode = if encoding == Encoding::Utf16Le { u16::from_le_bytes } else { u16::from_be_bytes };
+                let units: Vec<u16> = start.chunks_exact(2).map(|pair| decode([pair[0], pair[1]])).collect();
+                String::from_utf16_lossy(&units)
+            }
+            _ => String::from_utf8_lossy(start).into_owned(),
+        }
+    }
+
+    // "Jan  5 13:37:00 host sshd[42]: ..." (BSD syslog) or "2024-01-05T13:37:00 ..." (ISO 8601)
+    fn is_log_line(line: &str) -> bool {
+        const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+        let bytes = line.as_bytes();
+        let is_time = |time: &[u8]| time.len() >= 8 && time[2] == b':' && time[5] == b':'
+            && [0, 1, 3, 4, 6, 7].iter().all(|&i| time[i].is_ascii_digit());
+        if line.len() >= 15 && line.get(..3).is_some_and(|month| MONTHS.contains(&month)) && bytes[3] == b' ' {
+            return is_time(&bytes[7..]);
+        }
+        line.len() >= 19 && bytes[4] == b'-' && bytes[7] == b'-' && (bytes[10] == b'T' || bytes[10] == b' ')
+            && bytes[..4].iter().all(u8::is_ascii_digit) && is_time(&bytes[11..])
+    }
+
+    // The same number of separators in every line
+    fn is_csv(lines: &[&str]) -> bool {
+        lines.len() >= 3 && [',', ';', '\t'].iter().any(|&separator| {
+            let count = lines[0].matches(separator).count();
+            count > 0 && lines.iter().all(|line| line.matches(separator).count() == count)
+        })
+    }
+
+    // Typical lines of programming languages and their extensions
+    fn source_language(text: &str) -> Option<&'static str> {
+        const LANGUAGES: [(&str, &[&str]); 6] = [
+            ("c", &["#include <", "#include \"", "#define ", "int main("]),
+            ("rs", &["fn main()", "pub fn ", "impl ", "use std::"]),
+            ("py", &["def ", "import ", "from ", "if __name__"]),
+            ("java", &["public class ", "import java.", "package "]),
+            ("js", &["function ", "const ", "require(", "module.exports"]),
+            ("sh", &["#!/bin/", "fi\n", "esac\n"]),
+        ];
+        // The language with the most lines starting with its keywords, at least 3
+        LANGUAGES.iter()
+            .map(|(extension, keywords)| (extension, text.lines().filter(|line| keywords.iter().any(|keyword| line.trim_start().starts_with(keyword))).count()))
+            .filter(|&(_, count)| count >= 3)
+            .max_by_key(|&(_, count)| count)
+            .map(|(extension, _)| *extension)
+    }
+}
+
+impl Carver for TextCarver {
+    fn name(&self) -> &'static str {
+        "Text"
+    }
+
+    fn extension(&self) -> &'static str {
+        "txt"
+    }
+
+    fn extension_for(&self, data: &[u8]) -> &'static str {
+        let text = self.decode(data);
+        let trimmed = text.trim_matches(|c: char| c.is_whitespace() || c == '\u{FEFF}');
+        // The last line may be cut off in the 64 KiB we look at
+        let lines: Vec<&str> = trimmed.lines().take(20).collect();
+        let complete_lines = if lines.len() > 1 { &lines[..lines.len() - 1] } else { &lines[..] };
+
+        // Markup: a declaration or elements with closing tags
+        if trimmed.starts_with("<?xml") || (trimmed.starts_with('<') && trimmed.contains("</")) {
+            let start: String = trimmed.chars().take(256).collect();
+            if start.to_ascii_lowercase().contains("<html") {
+                return "html";
+            }
+            return "xml";
+        }
+        // An object starts with a key, an array with a value. "[section]" of INI files is neither.
+        let mut characters = trimmed.chars();
+        let first = characters.next();
+        let second = characters.find(|c| !c.is_whitespace());
+        match (first, second) {
+            (Some('{'), Some('"' | '}')) => return "json",
+            (Some('['), Some(c)) if "{[\"]-0123456789tfn".contains(c) => return "json",
+            _ => {}
+        }
+        if complete_lines.iter().filter(|line| TextCarver::is_log_line(line)).count() * 2 > complete_lines.len() {
+            return "log";
+        }
+        if TextCarver::is_csv(complete_lines) {
+            return "csv";
+        }
+        TextCarver::source_language(trimmed).unwrap_or(self.extension())
+    }
+
+    fn max_size(&self) -> usize {
+        100 * 1024 * 1024
+    }
+
+    fn find_header(&self, block: &[u8]) -> Option<usize> {
+        // Most unused rests of blocks are zeros, don't look any further. UTF-16 can start with one zero byte.
+        if block.len() < 2 || block[..2] == [0, 0] {
+            return None;
+        }
+        let encoding = text_encoding(block)?;
+        if trim_zeros(block, encoding.unit_size()).len() < MIN_TEXT_LENGTH * encoding.unit_size() {
+            return None;
+        }
+        Some(0)
+    }
+
+    fn starts_at_block(&self) -> bool {
+        true
+    }
+
+    fn find_end(&self, data: &[u8]) -> Scan {
+        match text_encoding(&data[..data.len().min(self.block_size)]) {
+            Some(encoding) => text_end(data, encoding, self.block_size),
+            None => Scan::Invalid,
+        }
+    }
+
+    fn find_end_at_eof(&self, data: &[u8]) -> Option<usize> {
+        text_end_at_eof(data, text_encoding(&data[..data.len().min(self.block_size)])?, self.block_size)
+    }
+}
@@ -0,0 +1,105 @@
+use super::{crc32, le_u32, Carver, Scan};
+
+/// xz stream: 12 byte header, blocks, an index and a 12 byte footer. The footer has the
+/// size of the index, which lists the size of every block, so the whole stream can be checked.
+pub struct XzCarver;
+
+const HEADER_MAGIC: &[u8] = b"\xFD7zXZ\0";
+const FOOTER_MAGIC: &[u8] = b"YZ";
+const HEADER_SIZE: usize = 12;
+const FOOTER_SIZE: usize = 12;
+
+// Multibyte integer of xz: 7 bits per byte, least significant first
+fn varint(data: &[u8], offset: &mut usize) -> Option<u64> {
+    let mut value = 0;
+    for i in 0..9 {
+        let byte = *data.get(*offset)?;
+        *offset += 1;
+        value |= ((byte & 0x7F) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+impl XzCarver {
+    // Checks the footer at offset: CRC-32, same flags as the header, and an index in front of it
+    // whose records add up to the blocks between header and index
+    fn is_stream_end(data: &[u8], footer: usize) -> bool {
+        let footer_data = &data[footer..footer + FOOTER_SIZE];
+        if &footer_data[10..] != FOOTER_MAGIC || footer_data[8..10] != data[6..8] {
+            return false;
+        }
+        if le_u32(footer_data, 0) != Some(crc32(&footer_data[4..10])) {
+            return false;
+        }
+        let index_size = (le_u32(footer_data, 4).unwrap() as usize + 1) * 4;
+        let index = match footer.checked_sub(index_size) {
+            Some(index) if index >= HEADER_SIZE => index,
+            _ => return false,
+        };
+
+        // Index indicator, number of records, records, padding, CRC-32
+        let index_data = &data[index..footer];
+        if index_data[0] != 0 || le_u32(index_data, index_size - 4) != Some(crc32(&index_data[..index_size - 4])) {
+            return false;
+        }
+        let mut offset = 1;
+        let records = match varint(index_data, &mut offset) {
+            Some(records) => records,
+            None => return false,
+        };
+        // Blocks are padded to a multiple of 4 bytes
+        let mut blocks_size = 0u64;
+        for _ in 0..records {
+            match (varint(index_data, &mut offset), varint(index_data, &mut offset)) {
+                (Some(unpadded_size), Some(_)) => {
+                    blocks_size = match unpadded_size.div_ceil(4).checked_mul(4).and_then(|size| blocks_size.checked_add(size)) {
+                        Some(blocks_size) => blocks_size,
+                        None => return false,
+                    };
+                }
+                _ => return false,
+            }
+        }
+        offset <= index_size - 4 && HEADER_SIZE as u64 + blocks_size == index as u64
+    }
+}
+
+impl Carver for XzCarver {
+    fn name(&self) -> &'static str {
+        "xz"
+    }
+
+    fn extension(&self) -> &'static str {
+        "xz"
+    }
+
+    fn max_size(&self) -> usize {
+        512 * 1024 * 1024
+    }
+
+    fn find_header(&self, block: &[u8]) -> Option<usize> {
+        // Magic, stream flags (only the check type is used) and their CRC-32
+        if block.len() >= HEADER_SIZE && block.starts_with(HEADER_MAGIC) && block[6] == 0 && block[7] & 0xF0 == 0
+            && le_u32(block, 8) == Some(crc32(&block[6..8]))
+        {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    fn find_end(&self, data: &[u8]) -> Scan {
+        // The size of a stream is a multiple of 4, the footer ends with "YZ"
+        let mut footer = HEADER_SIZE;
+        while footer + FOOTER_SIZE <= data.len() {
+            if XzCarver::is_stream_end(data, footer) {
+                return Scan::End(footer + FOOTER_SIZE);
+            }
+            footer += 4;
+        }
+        Scan::NeedMore
+    }
+}
@@ -0,0 +1,202 @@
+use super::{le_u16, le_u32, le_u64, Carver, Scan};
+
+/// ZIP: local file headers with the data, the central directory and the End of Central Directory
+/// record (EOCD) with an optional comment. DOCX, XLSX, PPTX, ODF, JAR and EPUB are ZIP files too.
+pub struct ZipCarver;
+
+const LOCAL_HEADER: &[u8] = b"PK\x03\x04";
+const CENTRAL_HEADER: &[u8] = b"PK\x01\x02";
+const EOCD: &[u8] = b"PK\x05\x06";
+const ZIP64_EOCD: &[u8] = b"PK\x06\x06";
+const ZIP64_LOCATOR: &[u8] = b"PK\x06\x07";
+
+// Fixed sizes of the records
+const LOCAL_HEADER_SIZE: usize = 30;
+const CENTRAL_HEADER_SIZE: usize = 46;
+const EOCD_SIZE: usize = 22;
+const ZIP64_LOCATOR_SIZE: usize = 20;
+
+// A file in the central directory
+struct Member {
+    name: String,
+    local_offset: usize,
+    compressed_size: usize,
+}
+
+impl ZipCarver {
+    // Parses the EOCD at eocd and the central directory it points to. All offsets are relative
+    // to the start of the archive, so they have to match the carved data: the central directory
+    // ends right in front of the EOCD and every member points back to a local header.
+    // Returns the members and the end of the archive including the comment.
+    fn central_directory(data: &[u8], eocd: usize) -> Option<(Vec<Member>, usize)> {
+        // No archives split over several disks
+        if le_u16(data, eocd + 4)? != 0 || le_u16(data, eocd + 6)? != 0 {
+            return None;
+        }
+        let mut count = le_u16(data, eocd + 10)? as usize;
+        let mut cd_size = le_u32(data, eocd + 12)? as usize;
+        let mut cd_offset = le_u32(data, eocd + 16)? as usize;
+        let end = eocd + EOCD_SIZE + le_u16(data, eocd + 20)? as usize;
+
+        // ZIP64: the real values are in the ZIP64 EOCD, which the locator in front of the EOCD points to
+        let mut cd_end = eocd;
+        if eocd >= ZIP64_LOCATOR_SIZE && data[eocd - ZIP64_LOCATOR_SIZE..].starts_with(ZIP64_LOCATOR) {
+            let zip64_eocd = le_u64(data, eocd - ZIP64_LOCATOR_SIZE + 8)? as usize;
+            if !data.get(zip64_eocd..)?.starts_with(ZIP64_EOCD) {
+                return None;
+            }
+            count = le_u64(data, zip64_eocd + 32)? as usize;
+            cd_size = le_u64(data, zip64_eocd + 40)? as usize;
+            cd_offset = le_u64(data, zip64_eocd + 48)? as usize;
+            cd_end = zip64_eocd;
+        }
+        if count == 0 || cd_offset.checked_add(cd_size)? != cd_end {
+            return None;
+        }
+
+        let mut members = vec![];
+        let mut offset = cd_offset;
+        for _ in 0..count {
+            if offset + CENTRAL_HEADER_SIZE > cd_end || !data[offset..].starts_with(CENTRAL_HEADER) {
+                return None;
+            }
+            let mut compressed_size = le_u32(data, offset + 20)? as usize;
+            let name_length = le_u16(data, offset + 28)? as usize;
+            let extra_length = le_u16(data, offset + 30)? as usize;
+            let comment_length = le_u16(data, offset + 32)? as usize;
+            let mut local_offset = le_u32(data, offset + 42)? as usize;
+
+            let name_start = offset + CENTRAL_HEADER_SIZE;
+            let extra_start = name_start + name_length;
+            let name = String::from_utf8_lossy(data.get(name_start..extra_start)?).into_owned();
+            let extra = data.get(extra_start..extra_start + extra_length)?;
+            (compressed_size, local_offset) = ZipCarver::zip64_sizes(extra, le_u32(data, offset + 24)?, compressed_size, local_offset)?;
+
+            if local_offset >= cd_offset || !data[local_offset..].starts_with(LOCAL_HEADER) {
+                return None;
+            }
+            members.push(Member { name, local_offset, compressed_size });
+            offset = extra_start + extra_length + comment_length;
+        }
+        if offset != cd_end {
+            return None;
+        }
+        Some((members, end))
+    }
+
+    // Sizes and offsets too big for 32 bit are 0xFFFFFFFF, the real values are in the ZIP64 extra
+    // field (0x0001), in the order uncompressed size, compressed size, local header offset
+    fn zip64_sizes(extra: &[u8], uncompressed_size: u32, compressed_size: usize, local_offset: usize) -> Option<(usize, usize)> {
+        const ZIP64_MARKER: usize = 0xFFFF_FFFF;
+        if compressed_size != ZIP64_MARKER && local_offset != ZIP64_MARKER {
+            return Some((compressed_size, local_offset));
+        }
+
+        let mut offset = 0;
+        while offset + 4 <= extra.len() {
+            let id = le_u16(extra, offset)?;
+            let size = le_u16(extra, offset + 2)? as usize;
+            if id == 0x0001 {
+                let mut field = offset + 4;
+                if uncompressed_size == 0xFFFF_FFFF {
+                    field += 8;
+                }
+                let mut compressed_size = compressed_size;
+                if compressed_size == ZIP64_MARKER {
+                    compressed_size = le_u64(extra, field)? as usize;
+                    field += 8;
+                }
+                let mut local_offset = local_offset;
+                if local_offset == ZIP64_MARKER {
+                    local_offset = le_u64(extra, field)? as usize;
+                }
+                return Some((compressed_size, local_offset));
+            }
+            offset += 4 + size;
+        }
+        None
+    }
+
+    // The first valid EOCD with its central directory, None if there is none in the data yet.
+    // EOCD signatures inside of stored members (e.g. a ZIP in a ZIP) don't match the offsets of the carved data.
+    fn find_central_directory(data: &[u8]) -> Option<(Vec<Member>, usize)> {
+        let mut search = LOCAL_HEADER_SIZE;
+        while let Some(found) = data[search.min(data.len())..].windows(4).position(|w| w == EOCD) {
+            let eocd = search + found;
+            if eocd + EOCD_SIZE > data.len() {
+                return None;
+            }
+            if let Some(central_directory) = ZipCarver::central_directory(data, eocd) {
+                return Some(central_directory);
+            }
+            search = eocd + EOCD.len();
+        }
+        None
+    }
+
+    // Content of a stored member, e.g. "mimetype"
+    fn member_data<'a>(data: &'a [u8], member: &Member) -> Option<&'a [u8]> {
+        let name_length = le_u16(data, member.local_offset + 26)? as usize;
+        let extra_length = le_u16(data, member.local_offset + 28)? as usize;
+        let start = member.local_offset + LOCAL_HEADER_SIZE + name_length + extra_length;
+        data.get(start..start + member.compressed_size)
+    }
+}
+
+impl Carver for ZipCarver {
+    fn name(&self) -> &'static str {
+        "ZIP"
+    }
+
+    fn extension(&self) -> &'static str {
+        "zip"
+    }
+
+    fn extension_for(&self, data: &[u8]) -> &'static str {
+        let members = ZipCarver::find_central_directory(data).map(|(members, _)| members).unwrap_or_default();
+
+        // ODF and EPUB start with a stored "mimetype" member
+        if let Some(mimetype) = members.iter().find(|member| member.name == "mimetype") {
+            match ZipCarver::member_data(data, mimetype) {
+                Some(b"application/epub+zip") => return "epub",
+                Some(b"application/vnd.oasis.opendocument.text") => return "odt",
+                Some(b"application/vnd.oasis.opendocument.spreadsheet") => return "ods",
+                Some(b"application/vnd.oasis.opendocument.presentation") => return "odp",
+                Some(b"application/vnd.oasis.opendocument.graphics") => return "odg",
+                _ => {}
+            }
+        }
+        // Office Open XML: the folder of the main part tells the application
+        if members.iter().any(|member| member.name == "[Content_Types].xml") {
+            for (folder, extension) in [("word/", "docx"), ("xl/", "xlsx"), ("ppt/", "pptx")] {
+                if members.iter().any(|member| member.name.starts_with(folder)) {
+                    return extension;
+                }
+            }
+        }
+        if members.iter().any(|member| member.name == "META-INF/MANIFEST.MF") {
+            return "jar";
+        }
+        "zip"
+    }
+
+    fn max_size(&self) -> usize {
+        512 * 1024 * 1024
+    }
+
+    fn find_header(&self, block: &[u8]) -> Option<usize> {
+        // The first local header is at the start of the archive, the file name can't be empty
+        if block.starts_with(LOCAL_HEADER) && le_u16(block, 4)? <= 63 && le_u16(block, 26)? > 0 {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    fn find_end(&self, data: &[u8]) -> Scan {
+        match ZipCarver::find_central_directory(data) {
+            Some((_, end)) => Scan::End(end),
+            None => Scan::NeedMore,
+        }
+    }
+}
@@ -2,8 +2,11 @@
 // see https://www.nongnu.org/ext2-doc/ext2.html for documentation on ext2 fs
 
 use std::{fs, io};
+use std::collections::HashMap;
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 #[derive(Debug)]
 pub struct Ext2FS {
@@ -11,9 +14,16 @@ pub struct Ext2FS {
     // The block group descriptor table is an array of block group descriptor, used to define parameters of all the block groups.
     #[allow(dead_code)]
     block_group_descriptors: Vec<BlockGroupDescriptor>,
-    inode_table: Vec<Inode>,
+    inode_table: Vec<Inode>, // Every inode of the file system, inode number n is at index n - 1
     block_bitmaps: Vec<Vec<u8>>, // A vector of block bitmaps for each block group
-    data_blocks_offsets: Vec<u32>,
+    inode_bitmaps: Vec<Vec<u8>>, // A vector of inode bitmaps for each block group
+    metadata_blocks: Vec<Range<u32>>, // Absolute block ranges holding metadata, sorted by start block
+    device: File, // The image, to read file data on demand
+}
+
+pub struct InodeIter<'a> {
+    ext2_fs: &'a Ext2FS,
+    inode_number: u32,
 }
 
 pub struct BlockIter<'a> {
@@ -21,19 +31,105 @@ pub struct BlockIter<'a> {
     current_group: usize,
     current_byte: usize,
     current_bit: usize,
-    block_number: usize,
 }
 
+// Superblock magic number, s_magic of every ext2 file system
+const EXT2_SUPER_MAGIC: u16 = 0xEF53;
+
+// s_state values
+const EXT2_VALID_FS: u16 = 1; // Unmounted cleanly
+const EXT2_ERROR_FS: u16 = 2; // Errors detected
+
+// s_feature_compat: the implementation may ignore these features
+const EXT2_FEATURE_COMPAT: [(u32, &str); 6] = [
+    (0x0001, "dir_prealloc"),
+    (0x0002, "imagic_inodes"),
+    (0x0004, "has_journal"),
+    (0x0008, "ext_attr"),
+    (0x0010, "resize_inode"),
+    (0x0020, "dir_index"),
+];
+
+// s_feature_incompat: we must refuse to read the file system if we don't know a set feature
+const EXT2_FEATURE_INCOMPAT_FILETYPE: u32 = 0x0002;
+const EXT2_FEATURE_INCOMPAT: [(u32, &str); 11] = [
+    (0x0001, "compression"),
+    (EXT2_FEATURE_INCOMPAT_FILETYPE, "filetype"),
+    (0x0004, "needs_recovery"),
+    (0x0008, "journal_dev"),
+    (0x0010, "meta_bg"),
+    (0x0040, "extent"),
+    (0x0080, "64bit"),
+    (0x0100, "mmp"),
+    (0x0200, "flex_bg"),
+    (0x0400, "ea_inode"),
+    (0x8000, "inline_data"),
+];
+// Everything we can read: directory entries with a file type byte
+const EXT2_SUPPORTED_INCOMPAT: u32 = EXT2_FEATURE_INCOMPAT_FILETYPE;
+
+// s_feature_ro_compat: only needed to write, we can always read such a file system
+const EXT2_FEATURE_RO_COMPAT_SPARSE_SUPER: u32 = 0x0001;
+const EXT2_FEATURE_RO_COMPAT: [(u32, &str); 7] = [
+    (EXT2_FEATURE_RO_COMPAT_SPARSE_SUPER, "sparse_super"),
+    (0x0002, "large_file"),
+    (0x0004, "btree_dir"),
+    (0x0008, "huge_file"),
+    (0x0010, "uninit_bg"),
+    (0x0020, "dir_nlink"),
+    (0x0040, "extra_isize"),
+];
+
 #[derive(Debug, Clone, Copy)]
 #[repr(C, packed)]
 pub struct Superblock {
     inodes_count: u32,        // Total number of inodes
     blocks_count: u32,        // Total number of blocks
+    r_blocks_count: u32,      // Number of blocks reserved for the super user
+    free_blocks_count: u32,   // Number of free blocks
+    free_inodes_count: u32,   // Number of free inodes
+    first_data_block: u32,    // Block number of the block containing the superblock
+    log_block_size: u32,      // block_size = 1024 << log_block_size
+    log_frag_size: i32,       // frag_size = 1024 << log_frag_size (or >> -log_frag_size)
     block_size: u32,          // Block size
     blocks_per_group: u32,    // Number of blocks per group
+    frags_per_group: u32,     // Number of fragments per group
     inodes_per_group: u32,    // Number of inodes per group
+    mtime: u32,               // Last time the file system was mounted (UNIX timestamp)
+    wtime: u32,               // Last write access to the file system (UNIX timestamp)
+    mnt_count: u16,           // Mounts since the last full verification
+    max_mnt_count: i16,       // Mounts allowed before a full check is required
+    magic: u16,               // Magic number, 0xEF53
+    state: u16,               // File system state (clean / errors)
+    errors: u16,              // What the driver should do when an error is detected
+    minor_rev_level: u16,     // Minor revision level
+    lastcheck: u32,           // Time of the last file system check (UNIX timestamp)
+    checkinterval: u32,       // Maximal time between file system checks (seconds)
+    creator_os: u32,          // OS that created the file system
     rev_level: u32,           // Revision level
+    def_resuid: u16,          // Default user ID for reserved blocks
+    def_resgid: u16,          // Default group ID for reserved blocks
+    first_ino: u32,           // First inode usable for standard files
     inode_size: u16,          // Size of inode structure
+    block_group_nr: u16,      // Block group hosting this superblock
+    feature_compat: u32,      // Compatible feature set
+    feature_incompat: u32,    // Incompatible feature set
+    feature_ro_compat: u32,   // Read-only compatible feature set
+    uuid: [u8; 16],           // Volume ID
+    volume_name: [u8; 16],    // Volume name, NUL terminated
+    last_mounted: [u8; 64],   // Directory where the file system was last mounted
+    algo_bitmap: u32,         // Compression algorithms
+    prealloc_blocks: u8,      // Blocks to preallocate for files
+    prealloc_dir_blocks: u8,  // Blocks to preallocate for directories
+    reserved_gdt_blocks: u16, // Blocks reserved after the BGDT for online resizing
+    journal_uuid: [u8; 16],   // UUID of the journal superblock (ext3)
+    journal_inum: u32,        // Inode number of the journal file (ext3)
+    journal_dev: u32,         // Device number of the journal file (ext3)
+    last_orphan: u32,         // First inode in the list of inodes to delete
+    hash_seed: [u32; 4],      // Seeds for the directory indexing hash
+    def_hash_version: u8,     // Default hash version for directory indexing
+    default_mount_opts: u32,  // Default mount options
+    first_meta_bg: u32,       // First meta block group
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -49,9 +145,8 @@ struct BlockGroupDescriptor {
     bg_reserved: [u8; 12],     // Reserved space for future use
 }
 
-#[derive(Debug, Clone, Copy)]
-#[repr(C, packed)]
-struct Inode {
+#[derive(Debug, Clone)]
+pub struct Inode {
     i_mode: u16,              // File mode (type and permissions)
     i_uid: u16,               // User ID of owner
     i_size: u32,              // Size of file in bytes
@@ -70,35 +165,224 @@ struct Inode {
     i_dir_acl: u32,           // Directory ACL or file size high bits
     i_faddr: u32,             // Fragment address
     i_osd2: [u8; 12],         // OS-dependent value
+    // -- Large inodes (s_inode_size > 128), 0 if not present --
+    i_extra_isize: u16,       // Size of the used part of the large inode area
+    i_ctime_extra: u32,       // Extra change time bits (nanoseconds << 2 | epoch)
+    i_mtime_extra: u32,       // Extra modification time bits (nanoseconds << 2 | epoch)
+    i_atime_extra: u32,       // Extra access time bits (nanoseconds << 2 | epoch)
+    i_crtime: u32,            // File creation time (UNIX timestamp)
+    i_crtime_extra: u32,      // Extra creation time bits (nanoseconds << 2 | epoch)
+    xattrs: Vec<Xattr>,       // Extended attributes stored in the inode after the extra fields
+}
+
+// i_mode file format bits
+const EXT2_S_IFMT: u16 = 0xF000;
+const EXT2_S_IFSOCK: u16 = 0xC000; // Socket
+const EXT2_S_IFLNK: u16 = 0xA000; // Symbolic link
+const EXT2_S_IFREG: u16 = 0x8000; // Regular file
+const EXT2_S_IFBLK: u16 = 0x6000; // Block device
+const EXT2_S_IFDIR: u16 = 0x4000; // Directory
+const EXT2_S_IFCHR: u16 = 0x2000; // Character device
+const EXT2_S_IFIFO: u16 = 0x1000; // FIFO
+
+// The root directory is always inode 2
+const EXT2_ROOT_INO: u32 = 2;
+
+// Directory entry file_type values
+pub const EXT2_FT_UNKNOWN: u8 = 0;
+pub const EXT2_FT_REG_FILE: u8 = 1;
+pub const EXT2_FT_DIR: u8 = 2;
+pub const EXT2_FT_CHRDEV: u8 = 3;
+pub const EXT2_FT_BLKDEV: u8 = 4;
+pub const EXT2_FT_FIFO: u8 = 5;
+pub const EXT2_FT_SOCK: u8 = 6;
+pub const EXT2_FT_SYMLINK: u8 = 7;
+
+// Number of direct block pointers in i_block, followed by single, double and triple indirect pointers
+const EXT2_NDIR_BLOCKS: usize = 12;
+const EXT2_IND_BLOCK: usize = 12;
+const EXT2_DIND_BLOCK: usize = 13;
+const EXT2_TIND_BLOCK: usize = 14;
+
+// Header of the extended attributes stored inside a large inode
+const EXT2_XATTR_MAGIC: u32 = 0xEA02_0000;
+
+#[derive(Debug, Clone)]
+struct Xattr {
+    name: String,             // Full name including the namespace prefix, e.g. "user.comment"
+    value: Vec<u8>,           // Raw attribute value
 }
 impl Superblock {
     fn new(block: &[u8]) -> Self {
+        let u16_at = |offset: usize| u16::from_le_bytes(block[offset..offset + 2].try_into().unwrap());
+        let u32_at = |offset: usize| u32::from_le_bytes(block[offset..offset + 4].try_into().unwrap());
+
         // Parse relevant fields from the Superblock
-        let inodes_count = u32::from_le_bytes(block[0..4].try_into().unwrap());
-        let blocks_count = u32::from_le_bytes(block[4..8].try_into().unwrap());
-        let log_block_size = u32::from_le_bytes(block[24..28].try_into().unwrap());
-        let blocks_per_group = u32::from_le_bytes(block[32..36].try_into().unwrap());
-        let inodes_per_group = u32::from_le_bytes(block[40..44].try_into().unwrap());
-        let rev_level = u32::from_le_bytes(block[76..80].try_into().unwrap());
-        let inode_size = u16::from_le_bytes(block[88..90].try_into().unwrap());
+        let inodes_count = u32_at(0);
+        let blocks_count = u32_at(4);
+        let r_blocks_count = u32_at(8);
+        let free_blocks_count = u32_at(12);
+        let free_inodes_count = u32_at(16);
+        let first_data_block = u32_at(20);
+        let log_block_size = u32_at(24);
+        let log_frag_size = u32_at(28) as i32;
+        let blocks_per_group = u32_at(32);
+        let frags_per_group = u32_at(36);
+        let inodes_per_group = u32_at(40);
+        let mtime = u32_at(44);
+        let wtime = u32_at(48);
+        let mnt_count = u16_at(52);
+        let max_mnt_count = u16_at(54) as i16;
+        let magic = u16_at(56);
+        let state = u16_at(58);
+        let errors = u16_at(60);
+        let minor_rev_level = u16_at(62);
+        let lastcheck = u32_at(64);
+        let checkinterval = u32_at(68);
+        let creator_os = u32_at(72);
+        let rev_level = u32_at(76);
+        let def_resuid = u16_at(80);
+        let def_resgid = u16_at(82);
+        // -- EXT2_DYNAMIC_REV Specific --
+        let first_ino = u32_at(84);
+        let inode_size = u16_at(88);
+        let block_group_nr = u16_at(90);
+        let feature_compat = u32_at(92);
+        let feature_incompat = u32_at(96);
+        let feature_ro_compat = u32_at(100);
+        let uuid = block[104..120].try_into().unwrap();
+        let volume_name = block[120..136].try_into().unwrap();
+        let last_mounted = block[136..200].try_into().unwrap();
+        let algo_bitmap = u32_at(200);
+        // -- Performance Hints --
+        let prealloc_blocks = block[204];
+        let prealloc_dir_blocks = block[205];
+        let reserved_gdt_blocks = u16_at(206);
+        // -- Journaling Support --
+        let journal_uuid = block[208..224].try_into().unwrap();
+        let journal_inum = u32_at(224);
+        let journal_dev = u32_at(228);
+        let last_orphan = u32_at(232);
+        // -- Directory Indexing Support --
+        let hash_seed = [u32_at(236), u32_at(240), u32_at(244), u32_at(248)];
+        let def_hash_version = block[252];
+        // -- Other options --
+        let default_mount_opts = u32_at(256);
+        let first_meta_bg = u32_at(260);
 
         // Calculate the block size#
         // The block size is computed using this 32bit value as the number of bits to shift left the value 1024. This value may only be non-negative.
-        let block_size = 1024 << log_block_size;
+        // Clamp the shift, so a garbage superblock can't overflow before validate() rejects it.
+        let block_size = 1024u32.checked_shl(log_block_size).unwrap_or(0);
 
         Superblock {
             inodes_count,
             blocks_count,
+            r_blocks_count,
+            free_blocks_count,
+            free_inodes_count,
+            first_data_block,
+            log_block_size,
+            log_frag_size,
             block_size,
             blocks_per_group,
+            frags_per_group,
             inodes_per_group,
+            mtime,
+            wtime,
+            mnt_count,
+            max_mnt_count,
+            magic,
+            state,
+            errors,
+            minor_rev_level,
+            lastcheck,
+            checkinterval,
+            creator_os,
             rev_level,
+            def_resuid,
+            def_resgid,
+            first_ino,
             inode_size,
+            block_group_nr,
+            feature_compat,
+            feature_incompat,
+            feature_ro_compat,
+            uuid,
+            volume_name,
+            last_mounted,
+            algo_bitmap,
+            prealloc_blocks,
+            prealloc_dir_blocks,
+            reserved_gdt_blocks,
+            journal_uuid,
+            journal_inum,
+            journal_dev,
+            last_orphan,
+            hash_seed,
+            def_hash_version,
+            default_mount_opts,
+            first_meta_bg,
         }
     }
 
+    /// Checks that this is an ext2 superblock we are able to read
+    fn validate(&self) -> io::Result<()> {
+        if self.magic() != EXT2_SUPER_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Not an ext2 file system: superblock magic is {:#06x}, expected {:#06x}",
+                    self.magic(),
+                    EXT2_SUPER_MAGIC
+                ),
+            ));
+        }
+
+        let unsupported = self.feature_incompat() & !EXT2_SUPPORTED_INCOMPAT;
+        if unsupported != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "Unsupported incompatible file system features: {}",
+                    feature_names(unsupported, &EXT2_FEATURE_INCOMPAT)
+                ),
+            ));
+        }
+
+        if self.block_size() == 0 || self.block_size() > 65536 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid superblock: s_log_block_size is {}", self.log_block_size()),
+            ));
+        }
+
+        let inode_size = self.inode_size() as u32;
+        if inode_size < 128 || !inode_size.is_power_of_two() || inode_size > self.block_size() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid superblock: inode size is {} bytes", inode_size),
+            ));
+        }
+
+        if self.blocks_per_group() == 0 || self.inodes_per_group() == 0 || self.blocks_count() <= self.first_data_block() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Invalid superblock: {} blocks, {} blocks per group, {} inodes per group, first data block {}",
+                    self.blocks_count(),
+                    self.blocks_per_group(),
+                    self.inodes_per_group(),
+                    self.first_data_block()
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+
     // Add getter methods to safely access fields
-    fn inodes_count(&self) -> u32 {
+    pub fn inodes_count(&self) -> u32 {
         self.inodes_count
     }
 
@@ -106,28 +390,145 @@ impl Superblock {
         self.blocks_count
     }
 
+    fn r_blocks_count(&self) -> u32 { self.r_blocks_count }
+    fn free_blocks_count(&self) -> u32 { self.free_blocks_count }
+    fn free_inodes_count(&self) -> u32 { self.free_inodes_count }
+    fn first_data_block(&self) -> u32 { self.first_data_block }
+    fn log_block_size(&self) -> u32 { self.log_block_size }
+
     pub fn block_size(&self) -> u32 {
         self.block_size
     }
 
+    fn frag_size(&self) -> u32 {
+        let log_frag_size = self.log_frag_size;
+        if log_frag_size >= 0 {
+            1024u32.checked_shl(log_frag_size as u32).unwrap_or(0)
+        } else {
+            1024u32.checked_shr(log_frag_size.unsigned_abs()).unwrap_or(0)
+        }
+    }
+
     fn blocks_per_group(&self) -> u32 { self.blocks_per_group }
+    fn frags_per_group(&self) -> u32 { self.frags_per_group }
     fn inodes_per_group(&self) -> u32 { self.inodes_per_group }
+    fn mtime(&self) -> u32 { self.mtime }
+    fn wtime(&self) -> u32 { self.wtime }
+    fn mnt_count(&self) -> u16 { self.mnt_count }
+    fn max_mnt_count(&self) -> i16 { self.max_mnt_count }
+    fn magic(&self) -> u16 { self.magic }
+    fn state(&self) -> u16 { self.state }
+    fn errors(&self) -> u16 { self.errors }
+    fn minor_rev_level(&self) -> u16 { self.minor_rev_level }
+    fn lastcheck(&self) -> u32 { self.lastcheck }
+    fn checkinterval(&self) -> u32 { self.checkinterval }
+    fn creator_os(&self) -> u32 { self.creator_os }
     fn rev_level(&self) -> u32 {
         self.rev_level
     }
-    fn inode_size(&self) -> u16 { self.inode_size }
+    fn def_resuid(&self) -> u16 { self.def_resuid }
+    fn def_resgid(&self) -> u16 { self.def_resgid }
+    // In revision 0 the first non-reserved inode is fixed to 11
+    fn first_ino(&self) -> u32 {
+        if self.rev_level() == 0 { 11 } else { self.first_ino }
+    }
+    // In revision 0 every inode has 128 bytes
+    fn inode_size(&self) -> u16 {
+        if self.rev_level() == 0 { 128 } else { self.inode_size }
+    }
+    fn block_group_nr(&self) -> u16 { self.block_group_nr }
+    fn feature_compat(&self) -> u32 { self.feature_compat }
+    fn feature_incompat(&self) -> u32 { self.feature_incompat }
+    fn feature_ro_compat(&self) -> u32 { self.feature_ro_compat }
+    fn uuid(&self) -> [u8; 16] { self.uuid }
+    fn volume_name(&self) -> String { c_string(&self.volume_name) }
+    fn last_mounted(&self) -> String { c_string(&self.last_mounted) }
+    fn algo_bitmap(&self) -> u32 { self.algo_bitmap }
+    fn prealloc_blocks(&self) -> u8 { self.prealloc_blocks }
+    fn prealloc_dir_blocks(&self) -> u8 { self.prealloc_dir_blocks }
+    fn reserved_gdt_blocks(&self) -> u16 { self.reserved_gdt_blocks }
+    fn journal_uuid(&self) -> [u8; 16] { self.journal_uuid }
+    fn journal_inum(&self) -> u32 { self.journal_inum }
+    fn journal_dev(&self) -> u32 { self.journal_dev }
+    fn last_orphan(&self) -> u32 { self.last_orphan }
+    fn hash_seed(&self) -> [u32; 4] { self.hash_seed }
+    fn def_hash_version(&self) -> u8 { self.def_hash_version }
+    fn default_mount_opts(&self) -> u32 { self.default_mount_opts }
+    fn first_meta_bg(&self) -> u32 { self.first_meta_bg }
+
+    // Number of blocks in the given group, the last group may be smaller than blocks_per_group
+    fn blocks_in_group(&self, group: usize) -> u32 {
+        let group_start = self.group_first_block(group);
+        self.blocks_per_group().min(self.blocks_count() - group_start)
+    }
+
+    // Absolute number of the first block of the given group
+    fn group_first_block(&self, group: usize) -> u32 {
+        self.first_data_block() + group as u32 * self.blocks_per_group()
+    }
+
+    // With sparse_super only groups 0, 1 and powers of 3, 5 and 7 carry a superblock and BGDT backup,
+    // otherwise every group has one.
+    fn group_has_superblock(&self, group: usize) -> bool {
+        if self.feature_ro_compat() & EXT2_FEATURE_RO_COMPAT_SPARSE_SUPER == 0 || group <= 1 {
+            return true;
+        }
+        [3, 5, 7].iter().any(|&base| {
+            let mut power = base;
+            while power < group {
+                power *= base;
+            }
+            power == group
+        })
+    }
+
+    fn state_name(&self) -> String {
+        let state = self.state();
+        let mut names = vec![];
+        if state & EXT2_VALID_FS != 0 {
+            names.push("clean");
+        } else {
+            names.push("not clean");
+        }
+        if state & EXT2_ERROR_FS != 0 {
+            names.push("with errors");
+        }
+        names.join(" ")
+    }
+
+    fn errors_name(&self) -> &'static str {
+        match self.errors() {
+            1 => "Continue",
+            2 => "Remount read-only",
+            3 => "Panic",
+            _ => "Unknown",
+        }
+    }
+
+    fn creator_os_name(&self) -> &'static str {
+        match self.creator_os() {
+            0 => "Linux",
+            1 => "Hurd",
+            2 => "Masix",
+            3 => "FreeBSD",
+            4 => "Lites",
+            _ => "Unknown",
+        }
+    }
+
+    fn rev_level_name(&self) -> &'static str {
+        match self.rev_level() {
+            0 => "good old revision",
+            1 => "dynamic revision",
+            _ => "unknown revision",
+        }
+    }
 
     #[allow(dead_code)]
     fn print_parsed_info(&self) {
         // Print the parsed Superblock information
         println!("\x1b[32mPrint the parsed Superblock information:\x1b[0m");
-        println!("Inodes Count: {}", self.inodes_count());
-        println!("Blocks Count: {}", self.blocks_count());
-        println!("Block Size: {} bytes", self.block_size());
-        println!("Blocks per group: {}", self.blocks_per_group());
-        println!("Inodes per group: {}", self.inodes_per_group());
-        println!("Revision Level: {}", self.rev_level());
-        println!("Inode size: {}", self.inode_size());
+        print!("{}", self.get_all_info());
     }
 
     pub fn get_all_info(&self) -> String {
@@ -135,26 +536,169 @@ impl Superblock {
             "Superblock Information:\n\
             Inodes Count: {}\n\
             Blocks Count: {}\n\
+            Reserved Blocks Count: {}\n\
+            Free Blocks Count: {}\n\
+            Free Inodes Count: {}\n\
+            First Data Block: {}\n\
             Block Size: {} bytes\n\
+            Fragment Size: {} bytes\n\
             Blocks per Group: {}\n\
+            Fragments per Group: {}\n\
             Inodes per Group: {}\n\
-            Revision Level: {}\n\
-            Inode Size: {} bytes\n",
+            Last Mount Time: {}\n\
+            Last Write Time: {}\n\
+            Mount Count: {}\n\
+            Maximum Mount Count: {}\n\
+            Magic: {:#06x}\n\
+            State: {} ({})\n\
+            Errors Behaviour: {} ({})\n\
+            Minor Revision Level: {}\n\
+            Last Check: {}\n\
+            Check Interval: {} seconds\n\
+            Creator OS: {} ({})\n\
+            Revision Level: {} ({})\n\
+            Default Reserved UID: {}\n\
+            Default Reserved GID: {}\n\
+            First Inode: {}\n\
+            Inode Size: {} bytes\n\
+            Block Group Number: {}\n\
+            Compatible Features: {:#x} ({})\n\
+            Incompatible Features: {:#x} ({})\n\
+            Read-only Compatible Features: {:#x} ({})\n\
+            UUID: {}\n\
+            Volume Name: {}\n\
+            Last Mounted On: {}\n\
+            Compression Algorithms: {:#x}\n\
+            Preallocated Blocks: {}\n\
+            Preallocated Directory Blocks: {}\n\
+            Reserved GDT Blocks: {}\n\
+            Journal UUID: {}\n\
+            Journal Inode: {}\n\
+            Journal Device: {}\n\
+            Last Orphan: {}\n\
+            Hash Seed: {:08x?}\n\
+            Default Hash Version: {}\n\
+            Default Mount Options: {:#x}\n\
+            First Meta Block Group: {}\n",
             self.inodes_count(),
             self.blocks_count(),
+            self.r_blocks_count(),
+            self.free_blocks_count(),
+            self.free_inodes_count(),
+            self.first_data_block(),
             self.block_size(),
+            self.frag_size(),
             self.blocks_per_group(),
+            self.frags_per_group(),
             self.inodes_per_group(),
+            format_time(self.mtime()),
+            format_time(self.wtime()),
+            self.mnt_count(),
+            self.max_mnt_count(),
+            self.magic(),
+            self.state(),
+            self.state_name(),
+            self.errors(),
+            self.errors_name(),
+            self.minor_rev_level(),
+            format_time(self.lastcheck()),
+            self.checkinterval(),
+            self.creator_os(),
+            self.creator_os_name(),
             self.rev_level(),
+            self.rev_level_name(),
+            self.def_resuid(),
+            self.def_resgid(),
+            self.first_ino(),
             self.inode_size(),
+            self.block_group_nr(),
+            self.feature_compat(),
+            feature_names(self.feature_compat(), &EXT2_FEATURE_COMPAT),
+            self.feature_incompat(),
+            feature_names(self.feature_incompat(), &EXT2_FEATURE_INCOMPAT),
+            self.feature_ro_compat(),
+            feature_names(self.feature_ro_compat(), &EXT2_FEATURE_RO_COMPAT),
+            format_uuid(&self.uuid()),
+            self.volume_name(),
+            self.last_mounted(),
+            self.algo_bitmap(),
+            self.prealloc_blocks(),
+            self.prealloc_dir_blocks(),
+            self.reserved_gdt_blocks(),
+            format_uuid(&self.journal_uuid()),
+            self.journal_inum(),
+            self.journal_dev(),
+            self.last_orphan(),
+            self.hash_seed(),
+            self.def_hash_version(),
+            self.default_mount_opts(),
+            self.first_meta_bg(),
         )
     }
 }
 
+// Lists the names of all feature flags set in `flags`, unknown bits are printed in hex
+fn feature_names(flags: u32, known: &[(u32, &str)]) -> String {
+    let mut names: Vec<String> = vec![];
+    let mut rest = flags;
+    for (flag, name) in known {
+        if flags & flag != 0 {
+            names.push(name.to_string());
+            rest &= !flag;
+        }
+    }
+    if rest != 0 {
+        names.push(format!("unknown {:#x}", rest));
+    }
+    if names.is_empty() {
+        return "none".to_string();
+    }
+    names.join(", ")
+}
+
+// Formats a UUID as xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx
+fn format_uuid(uuid: &[u8; 16]) -> String {
+    let hex: String = uuid.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+// Reads a NUL terminated string from a fixed size field
+fn c_string(data: &[u8]) -> String {
+    let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).to_string()
+}
+
+// Formats a UNIX timestamp as "<seconds> (YYYY-MM-DD HH:MM:SS UTC)"
+fn format_time(timestamp: u32) -> String {
+    if timestamp == 0 {
+        return "0 (never)".to_string();
+    }
+    // Convert days since 1970-01-01 to a civil date (Howard Hinnant's civil_from_days algorithm)
+    let days = (timestamp / 86400) as i64 + 719468;
+    let seconds = timestamp % 86400;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{} ({:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC)",
+        timestamp,
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
 impl BlockGroupDescriptor {
-    // Temporary(?) solution, because I don't want to store invalid BGDs, and while
-    // bg_block_bitmap == 0 is invalid - we will filter it out
-    fn new(data: &[u8]) -> Option<Self> {
+    fn new(data: &[u8]) -> Self {
         let bg_block_bitmap = u32::from_le_bytes(data[0..4].try_into().unwrap());
         let bg_inode_bitmap = u32::from_le_bytes(data[4..8].try_into().unwrap());
         let bg_inode_table = u32::from_le_bytes(data[8..12].try_into().unwrap());
@@ -164,12 +708,7 @@ impl BlockGroupDescriptor {
         let bg_pad = u16::from_le_bytes(data[18..20].try_into().unwrap());
         let bg_reserved = data[20..32].try_into().unwrap();
 
-        // Check if bg_block_bitmap is invalid
-        if bg_block_bitmap == 0 {
-            return None; // Return None if the block bitmap is invalid
-        }
-
-        Some(BlockGroupDescriptor {
+        BlockGroupDescriptor {
             bg_block_bitmap,
             bg_inode_bitmap,
             bg_inode_table,
@@ -178,7 +717,7 @@ impl BlockGroupDescriptor {
             bg_used_dirs_count,
             bg_pad,
             bg_reserved,
-        })
+        }
     }
 
     fn bg_block_bitmap(&self) -> u32 { self.bg_block_bitmap }
@@ -230,14 +769,9 @@ impl BlockGroupDescriptor {
     }
 }
 impl Inode {
-    fn new(data: &[u8]) -> Option<Self> {
+    // Unused and deleted inodes are parsed as well, they are what we want to recover
+    fn new(data: &[u8]) -> Self {
         let i_mode = u16::from_le_bytes(data[0..2].try_into().unwrap());
-
-        // Validate fields
-        if i_mode == 0 {
-            return None; // Return None if the inode is unused
-        }
-
         let i_uid = u16::from_le_bytes(data[2..4].try_into().unwrap());
         let i_size = u32::from_le_bytes(data[4..8].try_into().unwrap());
         let i_atime = u32::from_le_bytes(data[8..12].try_into().unwrap());
@@ -261,7 +795,36 @@ impl Inode {
         let i_faddr = u32::from_le_bytes(data[112..116].try_into().unwrap());
         let i_osd2: [u8; 12] = data[116..128].try_into().unwrap();
 
-        Some(Inode {
+        // Large inodes: i_extra_isize tells how many bytes after the first 128 are used by extra fields.
+        // A field is only valid, if it lies completely inside this area.
+        let mut i_extra_isize = 0;
+        if data.len() > 128 {
+            i_extra_isize = u16::from_le_bytes(data[128..130].try_into().unwrap());
+            // Garbage, ignore the large inode area
+            if 128 + i_extra_isize as usize > data.len() || i_extra_isize % 4 != 0 {
+                i_extra_isize = 0;
+            }
+        }
+        let extra_end = 128 + i_extra_isize as usize;
+        let extra_u32 = |offset: usize| {
+            if offset + 4 <= extra_end {
+                u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+            } else {
+                0
+            }
+        };
+        let i_ctime_extra = extra_u32(132);
+        let i_mtime_extra = extra_u32(136);
+        let i_atime_extra = extra_u32(140);
+        let i_crtime = extra_u32(144);
+        let i_crtime_extra = extra_u32(148);
+        let xattrs = if i_extra_isize > 0 {
+            Xattr::parse_in_inode(&data[extra_end..])
+        } else {
+            vec![]
+        };
+
+        Inode {
             i_mode,
             i_uid,
             i_size,
@@ -280,7 +843,14 @@ impl Inode {
             i_dir_acl,
             i_faddr,
             i_osd2,
-        })
+            i_extra_isize,
+            i_ctime_extra,
+            i_mtime_extra,
+            i_atime_extra,
+            i_crtime,
+            i_crtime_extra,
+            xattrs,
+        }
     }
 
     fn i_mode(&self) -> u16 { self.i_mode}
@@ -301,9 +871,84 @@ impl Inode {
     fn i_dir_acl(&self) -> u32 { self.i_dir_acl}
     fn i_faddr(&self) -> u32 { self.i_faddr}
     fn i_osd2(&self) -> [u8; 12] { self.i_osd2}
+    fn i_extra_isize(&self) -> u16 { self.i_extra_isize }
+    // The upper 30 bits of the *_extra fields are the nanoseconds
+    fn i_ctime_nsec(&self) -> u32 { self.i_ctime_extra >> 2 }
+    fn i_mtime_nsec(&self) -> u32 { self.i_mtime_extra >> 2 }
+    fn i_atime_nsec(&self) -> u32 { self.i_atime_extra >> 2 }
+    fn i_crtime(&self) -> u32 { self.i_crtime }
+    fn i_crtime_nsec(&self) -> u32 { self.i_crtime_extra >> 2 }
+    fn xattrs(&self) -> &[Xattr] { &self.xattrs }
+
+    /// True if the inode was never used: no mode, no times and no block pointers
+    pub fn is_empty(&self) -> bool {
+        self.i_mode() == 0 && self.i_ctime() == 0 && self.i_dtime() == 0 && self.i_block().iter().all(|&b| b == 0)
+    }
+
+    /// True if the inode describes a deleted file: the deletion time is set, or nothing links to it anymore
+    pub fn is_deleted(&self) -> bool {
+        self.i_mode() != 0 && (self.i_dtime() != 0 || self.i_links_count() == 0)
+    }
+
+    pub fn is_regular_file(&self) -> bool { self.i_mode() & EXT2_S_IFMT == EXT2_S_IFREG }
+    pub fn is_directory(&self) -> bool { self.i_mode() & EXT2_S_IFMT == EXT2_S_IFDIR }
+    pub fn is_symlink(&self) -> bool { self.i_mode() & EXT2_S_IFMT == EXT2_S_IFLNK }
+
+    /// File size in bytes. For regular files the upper 32 bits are stored in i_dir_acl (large_file).
+    pub fn size(&self) -> u64 {
+        if self.is_regular_file() {
+            (self.i_dir_acl() as u64) << 32 | self.i_size() as u64
+        } else {
+            self.i_size() as u64
+        }
+    }
+
+    // Short symlink targets are stored directly in i_block instead of a data block.
+    // Such a link has no data blocks, only maybe an extended attribute block.
+    fn is_fast_symlink(&self, block_size: u32) -> bool {
+        let ea_blocks = if self.i_file_acl() != 0 { block_size / 512 } else { 0 };
+        self.is_symlink() && self.i_blocks() == ea_blocks
+    }
+
+    /// Last access time, for restoring it on a recovered file
+    pub fn accessed(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::new(self.i_atime() as u64, self.i_atime_nsec())
+    }
+
+    /// Last modification time, for restoring it on a recovered file
+    pub fn modified(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::new(self.i_mtime() as u64, self.i_mtime_nsec())
+    }
+
+    /// The directory entry file type matching i_mode
+    pub fn file_type(&self) -> u8 {
+        match self.i_mode() & EXT2_S_IFMT {
+            EXT2_S_IFREG => EXT2_FT_REG_FILE,
+            EXT2_S_IFDIR => EXT2_FT_DIR,
+            EXT2_S_IFCHR => EXT2_FT_CHRDEV,
+            EXT2_S_IFBLK => EXT2_FT_BLKDEV,
+            EXT2_S_IFIFO => EXT2_FT_FIFO,
+            EXT2_S_IFSOCK => EXT2_FT_SOCK,
+            EXT2_S_IFLNK => EXT2_FT_SYMLINK,
+            _ => EXT2_FT_UNKNOWN,
+        }
+    }
+
+    pub fn status(&self) -> &'static str {
+        if self.is_empty() {
+            "unused"
+        } else if self.is_deleted() {
+            "deleted"
+        } else if self.i_mode() == 0 {
+            "cleared"
+        } else {
+            "in use"
+        }
+    }
     #[allow(dead_code)]
     fn print_parsed_info(&self) {
         println!("\x1b[32mParsed Inode Information:\x1b[0m");
+        println!("Status: {}", self.status());
         println!("Mode: {:#o}", self.i_mode());
         println!("User ID (UID): {}", self.i_uid());
         println!("File Size: {} bytes", self.i_size());
@@ -322,11 +967,21 @@ impl Inode {
         println!("Directory ACL: {:#x}", self.i_dir_acl());
         println!("Fragment Address: {:#x}", self.i_faddr());
         println!("OSD2: {:?}", self.i_osd2());
+        println!("Extra Inode Size: {}", self.i_extra_isize());
+        println!("Access Time Nanoseconds: {}", self.i_atime_nsec());
+        println!("Creation Time Nanoseconds: {}", self.i_ctime_nsec());
+        println!("Modification Time Nanoseconds: {}", self.i_mtime_nsec());
+        println!("File Creation Time: {}.{:09}", self.i_crtime(), self.i_crtime_nsec());
+        for xattr in self.xattrs() {
+            println!("Extended Attribute: {}", xattr.get_info());
+        }
     }
-    pub fn get_all_info(&self, inode_number: usize) -> String {
+    pub fn get_all_info(&self, inode_number: u32, is_allocated: bool) -> String {
         format!(
             "Inode Information:\n\
             Inode Number: {} \n\
+            Status: {}\n\
+            Allocated in Inode Bitmap: {}\n\
             File Mode: {:o}\n\
             User ID (UID): {}\n\
             File Size: {} bytes\n\
@@ -345,8 +1000,16 @@ impl Inode {
             Directory ACL: {}\n\
             Fragment Address: {}\n\
             OS-Dependent Value: {:?}\n\
+            Extra Inode Size: {}\n\
+            Access Time Nanoseconds: {}\n\
+            Creation Time Nanoseconds: {}\n\
+            Modification Time Nanoseconds: {}\n\
+            File Creation Time: {}.{:09}\n\
+            {}\
             \n\n\n",
             inode_number,
+            self.status(),
+            if is_allocated { "yes" } else { "no" },
             self.i_mode(),
             self.i_uid(),
             self.i_size(),
@@ -365,11 +1028,88 @@ impl Inode {
             self.i_dir_acl(),
             self.i_faddr(),
             self.i_osd2(),
+            self.i_extra_isize(),
+            self.i_atime_nsec(),
+            self.i_ctime_nsec(),
+            self.i_mtime_nsec(),
+            self.i_crtime(),
+            self.i_crtime_nsec(),
+            self.xattrs()
+                .iter()
+                .map(|xattr| format!("Extended Attribute: {}\n", xattr.get_info()))
+                .collect::<String>(),
         )
     }
 
 }
 
+impl Xattr {
+    // Parses the extended attributes after the extra fields of a large inode:
+    // a magic number, followed by the entries. Value offsets are relative to the first entry.
+    fn parse_in_inode(data: &[u8]) -> Vec<Xattr> {
+        let mut xattrs = vec![];
+        if data.len() < 4 || u32::from_le_bytes(data[0..4].try_into().unwrap()) != EXT2_XATTR_MAGIC {
+            return xattrs;
+        }
+        let entries = &data[4..];
+
+        let mut offset = 0;
+        // Every entry has a 16 byte header, the list ends with 4 zero bytes
+        while offset + 16 <= entries.len() && entries[offset..offset + 4] != [0, 0, 0, 0] {
+            let name_len = entries[offset] as usize;
+            let name_index = entries[offset + 1];
+            let value_offs = u16::from_le_bytes(entries[offset + 2..offset + 4].try_into().unwrap()) as usize;
+            let value_inum = u32::from_le_bytes(entries[offset + 4..offset + 8].try_into().unwrap());
+            let value_size = u32::from_le_bytes(entries[offset + 8..offset + 12].try_into().unwrap()) as usize;
+
+            let name_start = offset + 16;
+            if name_start + name_len > entries.len() {
+                break;
+            }
+            let name = String::from_utf8_lossy(&entries[name_start..name_start + name_len]);
+
+            // Values stored in a separate inode (ea_inode) are not supported
+            let value = if value_inum == 0 && value_offs + value_size <= entries.len() {
+                entries[value_offs..value_offs + value_size].to_vec()
+            } else {
+                vec![]
+            };
+
+            xattrs.push(Xattr {
+                name: format!("{}{}", Xattr::prefix(name_index), name),
+                value,
+            });
+
+            // Entries are padded to 4 bytes
+            offset = (name_start + name_len + 3) & !3;
+        }
+        xattrs
+    }
+
+    // Namespace prefix for e_name_index
+    fn prefix(name_index: u8) -> &'static str {
+        match name_index {
+            1 => "user.",
+            2 => "system.posix_acl_access",
+            3 => "system.posix_acl_default",
+            4 => "trusted.",
+            6 => "security.",
+            7 => "system.",
+            8 => "system.richacl",
+            _ => "unknown.",
+        }
+    }
+
+    fn get_info(&self) -> String {
+        // Print printable values as text, everything else as bytes
+        if !self.value.is_empty() && self.value.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+            format!("{} = \"{}\"", self.name, String::from_utf8_lossy(&self.value))
+        } else {
+            format!("{} = {:02x?}", self.name, self.value)
+        }
+    }
+}
+
 impl Ext2FS {
     pub fn new(mut _device: fs::File) -> io::Result<Ext2FS> {
         // Seek to the Superblock location (offset 1024 bytes)
@@ -379,6 +1119,8 @@ impl Ext2FS {
         let mut buffer = [0u8; 1024];
         _device.read_exact(&mut buffer)?;
         let superblock = Superblock::new(&buffer);
+        // Refuse anything that is not an ext2 file system we understand
+        superblock.validate()?;
 
         // For debug
         // superblock.print_parsed_info();
@@ -387,26 +1129,21 @@ impl Ext2FS {
         let block_size = superblock.block_size();
         // The block group descriptor table starts on the first block following the superblock.
         // This would be the third block on a 1KiB block file system, or the second block for 2KiB and larger block file systems.
-        let descriptor_table_offset = if block_size > 1024 {
-            block_size // Descriptor table is in block 2 if block size > 1024
-        } else {
-            2 * block_size // Otherwise, it's in block 3
-        };
+        // s_first_data_block is 1 for 1KiB blocks and 0 otherwise, so the table is always in block first_data_block + 1.
+        let descriptor_table_offset = (superblock.first_data_block() as u64 + 1) * block_size as u64;
 
         // Depending on how many block groups are defined, this table can require multiple blocks of storage. Always refer to the superblock in case of doubt.
-        // Calculate the number of block groups
-
-        // For now, because I see this:
-        // Block count:              2048
-        // Blocks per group:         8192
-        // And it's make no sense for me, how can I calculate block_groups_number
-        // I assume, that Block Group Descriptor Table can not span more than 1 block (in fact, according to the documentation, that's not true.)
-        let block_groups_number = block_size / 32; //superblock.blocks_count() / superblock.blocks_per_group();
+        // Calculate the number of block groups:
+        // every group (except maybe the last one) holds blocks_per_group blocks, counted from s_first_data_block on.
+        // The last group can be smaller, so we have to round up.
+        let block_groups_number = (superblock.blocks_count() - superblock.first_data_block())
+            .div_ceil(superblock.blocks_per_group());
         // Each Block Group Descriptor is 32 bytes in size.
+        // Reading 32 * block_groups_number bytes at once covers all blocks of the table.
         let mut buffer = vec![0u8; 32 * block_groups_number as usize];
 
         // Read the Block Group Descriptor Table
-        _device.seek(SeekFrom::Start(descriptor_table_offset as u64))?;
+        _device.seek(SeekFrom::Start(descriptor_table_offset))?;
         _device.read_exact(&mut buffer)?;
 
         let mut block_group_descriptors: Vec<BlockGroupDescriptor> = vec![];
@@ -415,13 +1152,14 @@ impl Ext2FS {
         for i in 0..block_groups_number {
             let start = (i as usize) * 32;
             let end = start + 32;
-            if let Some(descriptor) = BlockGroupDescriptor::new(&buffer[start..end]) {
-                block_group_descriptors.push(descriptor);
-            } else {
-                // Red text: "\x1b[31m ...  \x1b[0m"
-                println!("\x1b[31mInvalid Block Bitmap Block in Block Group {}\x1b[0m", i);
-                break; // Exit the loop if an invalid descriptor is encountered
+            let descriptor = BlockGroupDescriptor::new(&buffer[start..end]);
+
+            // bg_block_bitmap == 0 is invalid, the block bitmap can never be located in block 0.
+            // The descriptor is damaged, but the other groups can still be recovered.
+            if descriptor.bg_block_bitmap() == 0 {
+                eprintln!("Warning: invalid Block Bitmap Block in Block Group {}, its blocks are treated as used", i);
             }
+            block_group_descriptors.push(descriptor);
         }
 
         // for debug
@@ -440,12 +1178,17 @@ impl Ext2FS {
 
         let mut block_bitmaps: Vec<Vec<u8>> = Vec::new();
 
-        for (_, descriptor) in block_group_descriptors.iter().enumerate() {
-            let block_bitmap_offset = descriptor.bg_block_bitmap() * block_size;
+        for descriptor in block_group_descriptors.iter() {
+            // Without its bitmap we can't tell the free blocks of a group, so none of them is free
+            if descriptor.bg_block_bitmap() == 0 {
+                block_bitmaps.push(vec![0xFF; block_bitmap_size as usize]);
+                continue;
+            }
+            let block_bitmap_offset = descriptor.bg_block_bitmap() as u64 * block_size as u64;
 
             // Read the Block Bitmap
             let mut bitmap_buffer = vec![0u8; block_bitmap_size as usize];
-            _device.seek(SeekFrom::Start(block_bitmap_offset as u64))?;
+            _device.seek(SeekFrom::Start(block_bitmap_offset))?;
             _device.read_exact(&mut bitmap_buffer)?;
 
             // println!("Loaded Block Bitmap for Block Group {}: {:?}", i, bitmap_buffer);
@@ -456,43 +1199,79 @@ impl Ext2FS {
         // green text: "\x1b[32m ... \x1b[0m"
         // println!("\x1b[32mBlock bitmaps length: \x1b[0m{:?}", block_bitmaps.len());
 
-        // We need data_blocks_offsets for each group, to get the data blocks, that are located after Inode Table.
+        // The Inode Bitmap works the same way, bit 0 of byte 0 is the first inode of the group
+        let inode_bitmap_size = superblock.inodes_per_group().div_ceil(8);
+
+        let mut inode_bitmaps: Vec<Vec<u8>> = Vec::new();
+
+        for descriptor in block_group_descriptors.iter() {
+            let inode_bitmap_offset = descriptor.bg_inode_bitmap() as u64 * block_size as u64;
+
+            // Read the Inode Bitmap
+            let mut bitmap_buffer = vec![0u8; inode_bitmap_size as usize];
+            _device.seek(SeekFrom::Start(inode_bitmap_offset))?;
+            _device.read_exact(&mut bitmap_buffer)?;
+
+            inode_bitmaps.push(bitmap_buffer);
+        }
+
+        // We need to know, which blocks of each group are metadata, to get the data blocks:
+        // superblock (backup);
+        // block group descriptor table (backup) and the blocks reserved for it to grow;
+        // block bitmap;
+        // inode bitmap;
+        // inode table;
+        // The bitmaps and the inode table are not necessarily located in their own group, so we store absolute block ranges.
+        let descriptor_table_blocks = (32 * block_groups_number).div_ceil(block_size);
         // Inode Table Size (in bytes) = Inodes per Group * Inode Size
         // Or in blocks:
-        let inode_table_size = superblock.inodes_per_group() / (block_size / superblock.inode_size() as u32);
+        let inode_table_size = (superblock.inodes_per_group() * superblock.inode_size() as u32).div_ceil(block_size);
 
-        let mut data_blocks_offsets = Vec::new();
+        let mut metadata_blocks: Vec<Range<u32>> = Vec::new();
 
-        for (_, descriptor) in block_group_descriptors.iter().enumerate() {
-            // Calculate the data block start for this group
-            let data_block_offset = inode_table_size + descriptor.bg_inode_table();
-
-            data_blocks_offsets.push(data_block_offset);
+        for (i, descriptor) in block_group_descriptors.iter().enumerate() {
+            if superblock.group_has_superblock(i) {
+                let group_start = superblock.group_first_block(i);
+                let group_metadata_end = group_start + 1 + descriptor_table_blocks + superblock.reserved_gdt_blocks() as u32;
+                metadata_blocks.push(group_start..group_metadata_end);
+            }
+            metadata_blocks.push(descriptor.bg_block_bitmap()..descriptor.bg_block_bitmap() + 1);
+            metadata_blocks.push(descriptor.bg_inode_bitmap()..descriptor.bg_inode_bitmap() + 1);
+            metadata_blocks.push(descriptor.bg_inode_table()..descriptor.bg_inode_table() + inode_table_size);
+        }
 
+        // Sort and merge the ranges, so is_metadata_block() can use a binary search
+        metadata_blocks.sort_by_key(|range| range.start);
+        let mut merged_metadata_blocks: Vec<Range<u32>> = Vec::new();
+        for range in metadata_blocks {
+            match merged_metadata_blocks.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged_metadata_blocks.push(range),
+            }
         }
+        let metadata_blocks = merged_metadata_blocks;
 
         // for debug
         // green text: "\x1b[32m ... \x1b[0m"
-        // println!("\x1b[32mData Blocks Offsets length: \x1b[0m{}", data_blocks_offsets.len());
+        // println!("\x1b[32mMetadata blocks: \x1b[0m{:?}", metadata_blocks);
 
         // Save Inode Table
         let mut inode_table: Vec<Inode> = vec![];
         // Read the Inode Table
-        for (_, descriptor) in block_group_descriptors.iter().enumerate() {
-            let inode_table_offset = descriptor.bg_inode_table() * superblock.block_size();
+        // Each inode takes s_inode_size bytes (128 for revision 0), only the first 128 bytes are the classic inode
+        let inode_size = superblock.inode_size() as usize;
+        for descriptor in block_group_descriptors.iter() {
+            let inode_table_offset = descriptor.bg_inode_table() as u64 * superblock.block_size() as u64;
+
+            // Read the whole table of this group at once
+            let mut buffer = vec![0u8; superblock.inodes_per_group() as usize * inode_size];
+            _device.seek(SeekFrom::Start(inode_table_offset))?;
+            _device.read_exact(&mut buffer)?;
 
             // Iterate through each inode in the inode table
-            for inode_index in 0..superblock.inodes_per_group() {
-                // Inode Structure - 128 bytes
-                let inode_offset = inode_table_offset + inode_index * 128;
-                // Buffer for a single inode
-                let mut buffer = [0u8; 128];
-                _device.seek(SeekFrom::Start(inode_offset as u64))?;
-                _device.read_exact(&mut buffer)?;
-
-                if let Some(inode) = Inode::new(&buffer) {
-                    inode_table.push(inode);
-                }
+            // Keep every inode, so the position in inode_table gives the inode number
+            for inode_data in buffer.chunks_exact(inode_size) {
+                inode_table.push(Inode::new(inode_data));
             }
         }
 
@@ -510,11 +1289,386 @@ impl Ext2FS {
                 block_group_descriptors,
                 inode_table,
                 block_bitmaps,
-                data_blocks_offsets
+                inode_bitmaps,
+                metadata_blocks,
+                device: _device,
             }
         )
     }
 
+    /// Returns the inode with the given number, inode numbers start at 1.
+    /// Unused and deleted inodes are returned as well.
+    pub fn inode(&self, inode_number: u32) -> Option<&Inode> {
+        if inode_number == 0 {
+            return None;
+        }
+        self.inode_table.get(inode_number as usize - 1)
+    }
+
+    /// Reads one block of the file system
+    pub fn read_block(&self, block_number: u32) -> io::Result<Vec<u8>> {
+        let block_size = self.super_block.block_size();
+        let mut buffer = vec![0u8; block_size as usize];
+        // &File implements Read and Seek, so we don't need &mut self
+        let mut device = &self.device;
+        device.seek(SeekFrom::Start(block_number as u64 * block_size as u64))?;
+        device.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    // Reads the block pointer at position index from the indirect block
+    fn read_block_pointer(&self, indirect_block: u32, index: u32) -> io::Result<u32> {
+        // A hole, everything below it is a hole as well
+        if indirect_block == 0 {
+            return Ok(0);
+        }
+        self.check_block_number(indirect_block)?;
+
+        let offset = indirect_block as u64 * self.super_block.block_size() as u64 + index as u64 * 4;
+        let mut buffer = [0u8; 4];
+        let mut device = &self.device;
+        device.seek(SeekFrom::Start(offset))?;
+        device.read_exact(&mut buffer)?;
+        Ok(u32::from_le_bytes(buffer))
+    }
+
+    fn check_block_number(&self, block_number: u32) -> io::Result<()> {
+        if block_number >= self.super_block.blocks_count() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Block pointer {} is outside of the file system", block_number),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Maps the n-th block of a file to the block number on disk, 0 means a hole (sparse file).
+    /// i_block holds 12 direct pointers, then one single, one double and one triple indirect pointer.
+    pub fn file_block(&self, inode: &Inode, logical_block: u64) -> io::Result<u32> {
+        let pointers_per_block = (self.super_block.block_size() / 4) as u64;
+        let i_block = inode.i_block();
+
+        let mut index = logical_block;
+        let block_number = if index < EXT2_NDIR_BLOCKS as u64 {
+            i_block[index as usize]
+        } else {
+            index -= EXT2_NDIR_BLOCKS as u64;
+            if index < pointers_per_block {
+                self.read_block_pointer(i_block[EXT2_IND_BLOCK], index as u32)?
+            } else {
+                index -= pointers_per_block;
+                if index < pointers_per_block.pow(2) {
+                    let indirect = self.read_block_pointer(i_block[EXT2_DIND_BLOCK], (index / pointers_per_block) as u32)?;
+                    self.read_block_pointer(indirect, (index % pointers_per_block) as u32)?
+                } else {
+                    index -= pointers_per_block.pow(2);
+                    if index >= pointers_per_block.pow(3) {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!("Block {} is beyond the maximum file size", logical_block),
+                        ));
+                    }
+                    let double = self.read_block_pointer(i_block[EXT2_TIND_BLOCK], (index / pointers_per_block.pow(2)) as u32)?;
+                    let indirect = self.read_block_pointer(double, (index / pointers_per_block % pointers_per_block) as u32)?;
+                    self.read_block_pointer(indirect, (index % pointers_per_block) as u32)?
+                }
+            }
+        };
+
+        if block_number != 0 {
+            self.check_block_number(block_number)?;
+        }
+        Ok(block_number)
+    }
+
+    // Like inode(), but with an error for the caller to pass on
+    fn existing_inode(&self, inode_number: u32) -> io::Result<&Inode> {
+        self.inode(inode_number).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Inode {} does not exist", inode_number),
+            )
+        })
+    }
+
+    /// Returns all blocks of a file: data blocks and the indirect blocks pointing to them, without holes.
+    /// Only the blocks needed for the file size are followed.
+    pub fn inode_blocks(&self, inode: &Inode) -> io::Result<Vec<u32>> {
+        let block_size = self.super_block.block_size() as u64;
+        let mut remaining = inode.size().div_ceil(block_size);
+        let mut blocks = vec![];
+        let i_block = inode.i_block();
+
+        for &block_number in i_block[..EXT2_NDIR_BLOCKS].iter() {
+            if remaining == 0 {
+                return Ok(blocks);
+            }
+            if block_number != 0 {
+                self.check_block_number(block_number)?;
+                blocks.push(block_number);
+            }
+            remaining -= 1;
+        }
+        // Single, double and triple indirect
+        for (depth, block_number) in [(1, i_block[EXT2_IND_BLOCK]), (2, i_block[EXT2_DIND_BLOCK]), (3, i_block[EXT2_TIND_BLOCK])] {
+            if remaining == 0 {
+                break;
+            }
+            self.collect_indirect_blocks(block_number, depth, &mut remaining, &mut blocks)?;
+        }
+        Ok(blocks)
+    }
+
+    // Adds the indirect block and all blocks below it, depth 1 points to data blocks
+    fn collect_indirect_blocks(&self, block_number: u32, depth: u32, remaining: &mut u64, blocks: &mut Vec<u32>) -> io::Result<()> {
+        let pointers_per_block = (self.super_block.block_size() / 4) as u64;
+        // A hole stands for all data blocks below it
+        if block_number == 0 {
+            *remaining = remaining.saturating_sub(pointers_per_block.pow(depth));
+            return Ok(());
+        }
+        self.check_block_number(block_number)?;
+        blocks.push(block_number);
+
+        let data = self.read_block(block_number)?;
+        for pointer in data.chunks_exact(4) {
+            if *remaining == 0 {
+                break;
+            }
+            let pointer = u32::from_le_bytes(pointer.try_into().unwrap());
+            if depth == 1 {
+                if pointer != 0 {
+                    self.check_block_number(pointer)?;
+                    blocks.push(pointer);
+                }
+                *remaining -= 1;
+            } else {
+                self.collect_indirect_blocks(pointer, depth - 1, remaining, blocks)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Indirect blocks, that ext2 writes right in front of data block `index` of a file, outermost first.
+    /// The level is 1 for a single, 2 for a double and 3 for a triple indirect block.
+    /// The single indirect block follows the 12 direct blocks, the double indirect block follows the blocks
+    /// of the single one and comes with its first single indirect block, and so on.
+    pub fn indirect_blocks_before(&self, index: u64) -> &'static [u32] {
+        let pointers = (self.super_block.block_size() / 4) as u64;
+        let single = EXT2_NDIR_BLOCKS as u64;
+        let double = single + pointers;
+        let triple = double + pointers * pointers;
+
+        if index == single {
+            &[1]
+        } else if index == double {
+            &[2, 1]
+        } else if index == triple {
+            &[3, 2, 1]
+        } else if index > triple && (index - triple).is_multiple_of(pointers * pointers) {
+            &[2, 1]
+        } else if index > double && (index - double).is_multiple_of(pointers) {
+            &[1]
+        } else {
+            &[]
+        }
+    }
+
+    /// Checks if the data is an indirect block of the given level, that belongs to the blocks behind it.
+    /// `following(i)` is the block number of the i-th block behind it. Every used pointer has to be exactly
+    /// the block, where the next data block or lower level indirect block is, the rest of the block is zero.
+    pub fn looks_like_indirect_block(&self, data: &[u8], level: u32, mut following: impl FnMut(usize) -> Option<u32>) -> bool {
+        let pointers: Vec<u32> = data
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+
+        // The used pointers come first, the rest of the block is zero
+        let used = pointers.iter().position(|&pointer| pointer == 0).unwrap_or(pointers.len());
+        if used == 0 || pointers[used..].iter().any(|&pointer| pointer != 0) {
+            return false;
+        }
+
+        // Number of blocks behind one pointer, including the indirect blocks of the lower levels
+        let mut stride = 1usize;
+        for _ in 1..level {
+            stride = stride * pointers.len() + 1;
+        }
+        pointers[..used].iter().enumerate().all(|(i, &pointer)| following(i * stride) == Some(pointer))
+    }
+
+    /// Checks the block bitmap, if the block is allocated
+    pub fn is_block_used(&self, block_number: u32) -> bool {
+        let block_number = match block_number.checked_sub(self.super_block.first_data_block()) {
+            Some(block_number) => block_number,
+            None => return true, // The boot block in front of a 1KiB file system is never free
+        };
+        let group = (block_number / self.super_block.blocks_per_group()) as usize;
+        let index = (block_number % self.super_block.blocks_per_group()) as usize;
+        match self.block_bitmaps.get(group) {
+            Some(bitmap) => (bitmap[index / 8] >> (index % 8)) & 1 == 1,
+            None => true,
+        }
+    }
+
+    /// Checks the inode bitmap, if the inode is allocated
+    pub fn is_inode_used(&self, inode_number: u32) -> bool {
+        // Inode numbers start at 1
+        let index = match inode_number.checked_sub(1) {
+            Some(index) => index,
+            None => return false,
+        };
+        let group = (index / self.super_block.inodes_per_group()) as usize;
+        let index = (index % self.super_block.inodes_per_group()) as usize;
+        match self.inode_bitmaps.get(group) {
+            Some(bitmap) => (bitmap[index / 8] >> (index % 8)) & 1 == 1,
+            None => false,
+        }
+    }
+
+    /// Names of deleted files by inode number, taken from the directory entries of every directory.
+    /// Both deleted entries in the slack and live entries pointing to deleted inodes are used.
+    pub fn deleted_file_names(&self) -> HashMap<u32, String> {
+        let mut names = HashMap::new();
+        for inode_number in 1..=self.inode_table.len() as u32 {
+            let inode = self.inode(inode_number).unwrap();
+            if !inode.is_directory() {
+                continue;
+            }
+
+            // Directories can be damaged or already overwritten, take whatever we can get
+            let live = self.read_dir(inode_number).unwrap_or_default();
+            let deleted = self.read_deleted_dir_entries(inode_number).unwrap_or_default();
+            for entry in live.into_iter().chain(deleted) {
+                let name = entry.name();
+                if name == "." || name == ".." {
+                    continue;
+                }
+                if self.inode(entry.inode()).is_some_and(|inode| inode.is_deleted()) {
+                    names.entry(entry.inode()).or_insert(name);
+                }
+            }
+        }
+        names
+    }
+
+    /// Opens the data of an inode for reading
+    pub fn read_inode_data(&self, inode_number: u32) -> io::Result<InodeReader<'_>> {
+        Ok(InodeReader::new(self, self.existing_inode(inode_number)?))
+    }
+
+    /// Lists the entries of a directory, including "." and ".."
+    pub fn read_dir(&self, inode_number: u32) -> io::Result<Vec<DirEntry>> {
+        self.read_dir_entries(inode_number, false)
+    }
+
+    /// Lists the deleted entries, that are still left in the rec_len slack of a directory.
+    /// The inode of an entry can be found with inode(entry.inode()), it may be reused by now.
+    pub fn read_deleted_dir_entries(&self, inode_number: u32) -> io::Result<Vec<DirEntry>> {
+        self.read_dir_entries(inode_number, true)
+    }
+
+    fn read_dir_entries(&self, inode_number: u32, deleted: bool) -> io::Result<Vec<DirEntry>> {
+        let inode = self.existing_inode(inode_number)?;
+        if !inode.is_directory() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotADirectory,
+                format!("Inode {} is not a directory", inode_number),
+            ));
+        }
+
+        let mut data = vec![];
+        self.read_inode_data(inode_number)?.read_to_end(&mut data)?;
+
+        // Directory entries never span blocks, so parse every block on its own
+        let has_file_type = self.super_block.feature_incompat() & EXT2_FEATURE_INCOMPAT_FILETYPE != 0;
+        let mut entries = vec![];
+        for block in data.chunks(self.super_block.block_size() as usize) {
+            for mut entry in DirEntry::parse_block(block, has_file_type, self.super_block.inodes_count(), deleted) {
+                // Old style entries have no type byte, take it from the inode
+                if !has_file_type {
+                    if let Some(entry_inode) = self.inode(entry.inode) {
+                        entry.file_type = entry_inode.file_type();
+                    }
+                }
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Resolves an absolute path like "/path/to/file" to an inode number, starting at the root directory.
+    /// Symbolic links are not followed.
+    pub fn lookup(&self, path: &str) -> io::Result<u32> {
+        let mut inode_number = EXT2_ROOT_INO;
+        for component in path.split('/').filter(|component| !component.is_empty()) {
+            let entry = self
+                .read_dir(inode_number)?
+                .into_iter()
+                .find(|entry| entry.name == component.as_bytes());
+            match entry {
+                Some(entry) => inode_number = entry.inode,
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("{} not found in {}", component, path),
+                    ))
+                }
+            }
+        }
+        Ok(inode_number)
+    }
+
+    // Writes every path of the live directory tree below the directory, depth first
+    fn write_directory_tree(&self, file: &mut File, inode_number: u32, path: &str, visited: &mut Vec<u32>) -> io::Result<()> {
+        // Protect against loops in a corrupted file system
+        if visited.contains(&inode_number) {
+            return Ok(());
+        }
+        visited.push(inode_number);
+
+        let entries = match self.read_dir(inode_number) {
+            Ok(entries) => entries,
+            Err(e) => {
+                writeln!(file, "{}/ (inode {}): {}", path, inode_number, e)?;
+                return Ok(());
+            }
+        };
+        for entry in entries {
+            let name = entry.name();
+            if name == "." || name == ".." {
+                continue;
+            }
+            let entry_path = format!("{}/{}", path, name);
+            writeln!(file, "{} (inode {}, {})", entry_path, entry.inode(), entry.file_type_name())?;
+            if entry.file_type() == EXT2_FT_DIR {
+                self.write_directory_tree(file, entry.inode(), &entry_path, visited)?;
+            }
+        }
+
+        // Deleted entries, together with the current state of their inode
+        for entry in self.read_deleted_dir_entries(inode_number)? {
+            let inode_status = self.inode(entry.inode()).map(|inode| inode.status()).unwrap_or("invalid");
+            writeln!(
+                file,
+                "{}/{} (deleted entry, inode {} is {}, {})",
+                path,
+                entry.name(),
+                entry.inode(),
+                inode_status,
+                entry.file_type_name()
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Checks if the block holds file system metadata (superblock, BGDT, bitmaps or inode table)
+    pub fn is_metadata_block(&self, block_number: u32) -> bool {
+        // Find the last range starting at or before block_number
+        let index = self.metadata_blocks.partition_point(|range| range.start <= block_number);
+        index > 0 && self.metadata_blocks[index - 1].contains(&block_number)
+    }
+
     /// Creates the debug_os_info folder and generates the .txt files
     pub fn create_debug_os_info(&self) -> io::Result<()> {
         // Create debug_os_info directory if it doesn't exist
@@ -526,7 +1680,7 @@ impl Ext2FS {
             fs::remove_dir_all(folder_path)?;
         }
 
-        fs::create_dir(&folder_path)?;
+        fs::create_dir(folder_path)?;
 
         // superblock_info
 
@@ -563,15 +1717,53 @@ impl Ext2FS {
             .create(true)
             .append(true)
             .open(&file_path)?;
-        for (i, inode) in self.inode_table.iter().enumerate() {
-            let info = inode.get_all_info(i); // Get the descriptor information
-            file.write_all(info.as_bytes())?;      // Write to the file
+        // Skip inodes, that were never used
+        for (_, inode_number, is_allocated, inode) in InodeIter::new(self) {
+            if let Some(inode) = inode {
+                let info = inode.get_all_info(inode_number, is_allocated); // Get the inode information
+                file.write_all(info.as_bytes())?;      // Write to the file
+            }
         }
 
+
+        // directory_tree
+
+        // Create a .txt file in the folder and write every path of the live tree to it
+        let file_path = format!("{}/directory_tree.txt", folder_path);
+        let mut file = File::create(&file_path)?;
+        self.write_directory_tree(&mut file, EXT2_ROOT_INO, "", &mut vec![])?;
+
         Ok(())
     }
 }
 
+impl<'a> InodeIter<'a> {
+    pub fn new(ext2_fs: &'a Ext2FS) -> Self {
+        InodeIter {
+            ext2_fs,
+            inode_number: 1,
+        }
+    }
+}
+impl<'a> Iterator for InodeIter<'a> {
+    // (Group Number, Inode Number, Is Allocated, Inode or None if the inode was never used)
+    type Item = (usize, u32, bool, Option<&'a Inode>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let inode_number = self.inode_number;
+        let inode = self.ext2_fs.inode(inode_number)?;
+        self.inode_number += 1;
+
+        let group = ((inode_number - 1) / self.ext2_fs.super_block.inodes_per_group()) as usize;
+        let is_allocated = self.ext2_fs.is_inode_used(inode_number);
+
+        // Inodes, that never held anything, are only interesting for their bitmap bit
+        let inode = if inode.is_empty() { None } else { Some(inode) };
+
+        Some((group, inode_number, is_allocated, inode))
+    }
+}
+
 impl<'a> BlockIter<'a> {
     pub fn new(ext2_fs: &'a Ext2FS) -> Self {
         BlockIter {
@@ -579,7 +1771,6 @@ impl<'a> BlockIter<'a> {
             current_group: 0,
             current_byte: 0,
             current_bit: 0,
-            block_number: 0
         }
     }
 }
@@ -587,51 +1778,50 @@ impl Iterator for BlockIter<'_> {
     type Item = (usize, usize, bool); // (Group Number, Block Number, Is Used)
 
     fn next(&mut self) -> Option<Self::Item> {
+        let super_block = &self.ext2_fs.super_block;
+
         // Check every group in block_bitmaps, we need every unused block
         while self.current_group < self.ext2_fs.block_bitmaps.len() {
             // Extract group from vector
             let group_bitmap = &self.ext2_fs.block_bitmaps[self.current_group];
-            // We can skip block, what are used for metadata:
-            // superblock;
-            // block group descriptor table;
-            // block bitmap;
-            // inode bitmap;
-            // inode table;
-            // We need -> data blocks
-            let blocks_to_skip = self.ext2_fs.data_blocks_offsets[self.current_group];
+            // Bit 0 of the bitmap is the first block of the group, not block 0
+            let group_start = super_block.group_first_block(self.current_group);
+            // The last group can be shorter, the rest of its bitmap is padding
+            let blocks_in_group = super_block.blocks_in_group(self.current_group) as usize;
 
             // Check every byte in group
-            while self.current_byte < group_bitmap.len() {
+            while self.current_byte < group_bitmap.len() && self.current_byte * 8 < blocks_in_group {
                 // Extract byte from vector
                 let group_byte = group_bitmap[self.current_byte];
 
                 // Extract every bit from byte
                 while self.current_bit < 8 {
-                    // Calculate current block number
-                    let current_block_number = self.block_number + 1;
-
-
-                    // Skip blocks with metadata
-                    if current_block_number <= blocks_to_skip as usize {
-                        self.block_number += 1;
-                        self.current_bit += 1;
-                        continue;
+                    let index = self.current_byte * 8 + self.current_bit;
+                    if index >= blocks_in_group {
+                        break;
                     }
+                    // Calculate current block number
+                    let current_block_number = group_start + index as u32;
 
                     // Extract the bit at current_bit
                     let bit = (group_byte >> self.current_bit) & 1;
 
-
                     // Next bit
-                    self.block_number += 1;
                     self.current_bit += 1;
 
+                    // We can skip blocks, that are used for metadata:
+                    // superblock, block group descriptor table, block bitmap, inode bitmap, inode table
+                    // We need -> data blocks
+                    if self.ext2_fs.is_metadata_block(current_block_number) {
+                        continue;
+                    }
+
                     // Return the current block info
                     return Some((
-                        self.current_group + 1,    // Block group number (1-based)
-                        current_block_number,     // Block number
-                        bit == 1,                 // Is Used
-                        ));
+                        self.current_group,            // Block group number
+                        current_block_number as usize, // Block number
+                        bit == 1,                      // Is Used
+                    ));
                 }
 
                 // Next byte
@@ -640,9 +1830,330 @@ impl Iterator for BlockIter<'_> {
             }
 
             // Next group
+            self.current_bit = 0;
             self.current_byte = 0;
             self.current_group += 1;
         }
         None
     }
 }
+
+/// A directory entry (ext2_dir_entry_2)
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    inode: u32,     // Inode number, 0 if the entry is unused
+    rec_len: u16,   // Displacement to the next entry
+    name_len: u16,  // Length of the name
+    file_type: u8,  // EXT2_FT_*, 0 if unknown
+    name: Vec<u8>,  // Name, not NUL terminated
+}
+
+impl DirEntry {
+    // Parses one directory block. Returns all used entries, or with deleted = true the deleted entries found in the slack.
+    // Without the filetype feature, name_len is 16 bit and there is no type byte.
+    fn parse_block(block: &[u8], has_file_type: bool, inodes_count: u32, deleted: bool) -> Vec<DirEntry> {
+        let mut entries = vec![];
+        let mut offset = 0;
+
+        // Every entry has an 8 byte header
+        while let Some(entry) = DirEntry::parse_entry(block, offset, has_file_type) {
+            let rec_len = entry.rec_len as usize;
+
+            // A broken record length would send us anywhere, give up on this block
+            if rec_len < 8 || !rec_len.is_multiple_of(4) || offset + rec_len > block.len() || 8 + entry.name_len as usize > rec_len {
+                break;
+            }
+
+            if deleted {
+                // Deleting an entry adds its rec_len to the previous entry, so everything behind the
+                // real length of an entry may still hold deleted entries
+                let slack_start = offset + entry.real_len();
+                entries.extend(DirEntry::parse_slack(&block[..offset + rec_len], slack_start, has_file_type, inodes_count));
+            } else if entry.inode != 0 {
+                entries.push(entry);
+            }
+
+            offset += rec_len;
+        }
+        entries
+    }
+
+    // Reads the entry at offset without any checks, None if the header doesn't fit
+    fn parse_entry(block: &[u8], offset: usize, has_file_type: bool) -> Option<DirEntry> {
+        if offset + 8 > block.len() {
+            return None;
+        }
+        let inode = u32::from_le_bytes(block[offset..offset + 4].try_into().unwrap());
+        let rec_len = u16::from_le_bytes(block[offset + 4..offset + 6].try_into().unwrap());
+        let (name_len, file_type) = if has_file_type {
+            (block[offset + 6] as u16, block[offset + 7])
+        } else {
+            (u16::from_le_bytes(block[offset + 6..offset + 8].try_into().unwrap()), EXT2_FT_UNKNOWN)
+        };
+        let name_end = (offset + 8 + name_len as usize).min(block.len());
+        let name = block[offset + 8..name_end].to_vec();
+
+        Some(DirEntry { inode, rec_len, name_len, file_type, name })
+    }
+
+    // Searches the slack of a live entry (up to the end of `block`) for plausible deleted entries
+    fn parse_slack(block: &[u8], start: usize, has_file_type: bool, inodes_count: u32) -> Vec<DirEntry> {
+        let mut entries = vec![];
+        // Entries are always 4 byte aligned
+        let mut offset = (start + 3) & !3;
+
+        while let Some(entry) = DirEntry::parse_entry(block, offset, has_file_type) {
+            if entry.is_plausible(block.len() - offset, has_file_type, inodes_count) {
+                // A deleted entry can have slack with even older entries, continue behind its name
+                offset += entry.real_len();
+                entries.push(entry);
+            } else {
+                offset += 4;
+            }
+        }
+        entries
+    }
+
+    // Checks if the bytes look like a real entry: valid name_len, printable name and an inode number in range
+    fn is_plausible(&self, space: usize, has_file_type: bool, inodes_count: u32) -> bool {
+        let name_len = self.name_len as usize;
+        let rec_len = self.rec_len as usize;
+        if name_len == 0 || name_len > 255 || self.real_len() > space {
+            return false;
+        }
+        if rec_len < self.real_len() || !rec_len.is_multiple_of(4) {
+            return false;
+        }
+        if self.inode == 0 || self.inode > inodes_count {
+            return false;
+        }
+        if has_file_type && self.file_type > EXT2_FT_SYMLINK {
+            return false;
+        }
+        // Names may contain any byte except NUL and '/', but deleted garbage mostly contains control characters
+        match std::str::from_utf8(&self.name) {
+            Ok(name) => !name.chars().any(|c| c.is_control() || c == '/'),
+            Err(_) => false,
+        }
+    }
+
+    // Length of the entry without slack, header and name rounded up to 4 bytes
+    fn real_len(&self) -> usize {
+        (8 + self.name_len as usize + 3) & !3
+    }
+
+    pub fn inode(&self) -> u32 { self.inode }
+    #[allow(dead_code)]
+    pub fn rec_len(&self) -> u16 { self.rec_len }
+    #[allow(dead_code)]
+    pub fn name_len(&self) -> u16 { self.name_len }
+    pub fn file_type(&self) -> u8 { self.file_type }
+    pub fn name(&self) -> String { String::from_utf8_lossy(&self.name).to_string() }
+
+    pub fn file_type_name(&self) -> &'static str {
+        match self.file_type() {
+            EXT2_FT_REG_FILE => "file",
+            EXT2_FT_DIR => "directory",
+            EXT2_FT_CHRDEV => "character device",
+            EXT2_FT_BLKDEV => "block device",
+            EXT2_FT_FIFO => "fifo",
+            EXT2_FT_SOCK => "socket",
+            EXT2_FT_SYMLINK => "symlink",
+            _ => "unknown",
+        }
+    }
+}
+
+/// Reads the data of a file, following the block pointers of its inode
+pub struct InodeReader<'a> {
+    ext2_fs: &'a Ext2FS,
+    inode: &'a Inode,
+    size: u64,
+    position: u64,
+}
+
+impl<'a> InodeReader<'a> {
+    pub fn new(ext2_fs: &'a Ext2FS, inode: &'a Inode) -> Self {
+        InodeReader {
+            ext2_fs,
+            inode,
+            size: inode.size(),
+            position: 0,
+        }
+    }
+}
+
+impl Read for InodeReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Never read behind the end of the file, the rest of the last block is garbage
+        if self.position >= self.size || buf.is_empty() {
+            return Ok(0);
+        }
+        let remaining = self.size - self.position;
+
+        // The target of a fast symlink is stored in i_block itself
+        if self.inode.is_fast_symlink(self.ext2_fs.super_block.block_size()) {
+            let target: Vec<u8> = self.inode.i_block().iter().flat_map(|pointer| pointer.to_le_bytes()).collect();
+            let start = (self.position as usize).min(target.len());
+            let length = buf.len().min(remaining as usize).min(target.len() - start);
+            buf[..length].copy_from_slice(&target[start..start + length]);
+            self.position += length as u64;
+            return Ok(length);
+        }
+
+        // Read at most up to the end of the current block
+        let block_size = self.ext2_fs.super_block.block_size() as u64;
+        let logical_block = self.position / block_size;
+        let offset_in_block = self.position % block_size;
+        let length = (block_size - offset_in_block).min(remaining).min(buf.len() as u64) as usize;
+
+        let block_number = self.ext2_fs.file_block(self.inode, logical_block)?;
+        if block_number == 0 {
+            // Holes read as zeros
+            buf[..length].fill(0);
+        } else {
+            let mut device = &self.ext2_fs.device;
+            device.seek(SeekFrom::Start(block_number as u64 * block_size + offset_in_block))?;
+            device.read_exact(&mut buf[..length])?;
+        }
+
+        self.position += length as u64;
+        Ok(length)
+    }
+}
+
+impl Seek for InodeReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.size.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        match new_position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A directory entry with file type, rec_len includes the slack behind the name
+    fn dir_entry(inode: u32, rec_len: u16, name: &str) -> Vec<u8> {
+        let mut entry = inode.to_le_bytes().to_vec();
+        entry.extend_from_slice(&rec_len.to_le_bytes());
+        entry.extend_from_slice(&[name.len() as u8, EXT2_FT_REG_FILE]);
+        entry.extend_from_slice(name.as_bytes());
+        entry.resize((8 + name.len() + 3) & !3, 0);
+        entry
+    }
+
+    #[test]
+    fn deleted_entries_in_slack() {
+        // "live" took over the space of "old.txt", which took over the space of "older"
+        let mut block = dir_entry(11, 64, "live");
+        block.extend(dir_entry(12, 52, "old.txt"));
+        block.extend(dir_entry(13, 36, "older"));
+        block.resize(64, 0);
+
+        let entries = DirEntry::parse_slack(&block, 12, true, 100);
+        let names: Vec<(u32, String)> = entries.iter().map(|entry| (entry.inode(), entry.name())).collect();
+        assert_eq!(names, [(12, "old.txt".to_string()), (13, "older".to_string())]);
+    }
+
+    #[test]
+    fn garbage_in_slack() {
+        let mut block = dir_entry(11, 64, "live");
+        // Inode number out of range, a control character in the name, no name
+        block.extend(dir_entry(500, 16, "gone"));
+        block.extend(dir_entry(14, 12, "a\x01b"));
+        block.extend(dir_entry(15, 12, ""));
+        block.resize(64, 0);
+        assert!(DirEntry::parse_slack(&block, 12, true, 100).is_empty());
+
+        // Entries are 4 byte aligned, the search starts at the next multiple of 4
+        let mut block = dir_entry(11, 32, "live");
+        block.extend(dir_entry(16, 20, "moved"));
+        block.resize(32, 0);
+        assert_eq!(DirEntry::parse_slack(&block, 9, true, 100).len(), 1);
+    }
+
+    #[test]
+    fn slack_without_file_type() {
+        // name_len is 16 bit, the file type is unknown
+        let mut block = dir_entry(11, 32, "live");
+        let mut old = dir_entry(12, 20, "old.c");
+        old[7] = 0;
+        block.extend(old);
+        block.resize(32, 0);
+
+        let entries = DirEntry::parse_slack(&block, 12, false, 100);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name(), "old.c");
+        assert_eq!(entries[0].file_type(), EXT2_FT_UNKNOWN);
+    }
+
+    // The small example has 1 KiB blocks, 256 pointers per indirect block
+    fn small_example() -> Ext2FS {
+        let device = File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/examples/small.img")).unwrap();
+        Ext2FS::new(device).unwrap()
+    }
+
+    // An indirect block with the pointers, the rest of the block is zero
+    fn indirect_block(pointers: &[u32]) -> Vec<u8> {
+        let mut block: Vec<u8> = pointers.iter().flat_map(|pointer| pointer.to_le_bytes()).collect();
+        block.resize(1024, 0);
+        block
+    }
+
+    #[test]
+    fn indirect_blocks_before() {
+        let ext2_fs = small_example();
+        let expected: [(u64, &[u32]); 9] = [
+            (0, &[]),
+            (11, &[]),
+            (12, &[1]),
+            (13, &[]),
+            (12 + 256, &[2, 1]),
+            (12 + 2 * 256, &[1]),
+            (12 + 256 + 256 * 256, &[3, 2, 1]),
+            (12 + 256 + 256 * 256 + 256, &[1]),
+            (12 + 256 + 2 * 256 * 256, &[2, 1]),
+        ];
+        for (index, levels) in expected {
+            assert_eq!(ext2_fs.indirect_blocks_before(index), levels, "data block {}", index);
+        }
+    }
+
+    #[test]
+    fn single_indirect_block() {
+        let ext2_fs = small_example();
+        let following = |i: usize| Some(100 + i as u32);
+        assert!(ext2_fs.looks_like_indirect_block(&indirect_block(&[100, 101, 102]), 1, following));
+        // A gap in the free blocks, or pointers to other blocks
+        assert!(!ext2_fs.looks_like_indirect_block(&indirect_block(&[100, 102, 103]), 1, following));
+        assert!(!ext2_fs.looks_like_indirect_block(&indirect_block(&[100, 101, 102]), 1, |i| Some(200 + i as u32)));
+        // More pointers than free blocks
+        assert!(!ext2_fs.looks_like_indirect_block(&indirect_block(&[100, 101, 102]), 1, |i| (i < 2).then_some(100 + i as u32)));
+        // No pointers, or data behind the first zero
+        assert!(!ext2_fs.looks_like_indirect_block(&indirect_block(&[]), 1, following));
+        assert!(!ext2_fs.looks_like_indirect_block(&indirect_block(&[100, 0, 102]), 1, following));
+    }
+
+    #[test]
+    fn double_indirect_block() {
+        let ext2_fs = small_example();
+        let following = |i: usize| Some(100 + i as u32);
+        // Every single indirect block is followed by its 256 data blocks
+        assert!(ext2_fs.looks_like_indirect_block(&indirect_block(&[100, 357, 614]), 2, following));
+        assert!(!ext2_fs.looks_like_indirect_block(&indirect_block(&[100, 101, 102]), 2, following));
+    }
+}
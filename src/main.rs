@@ -1,97 +1,170 @@
 use std::{io, fs};
-use std::io::{Read, Seek, Write};
 
+mod carve;
 mod ext2;
 
 
-fn recover_files(mut _device: fs::File, _path: &str) -> io::Result<()> {
-	// TODO: read superblock, iterate over unused blocks, find jpg start and end patterns, copy data
-
+fn recover_files(_device: fs::File, _path: &str, sqlite_pages: bool) -> io::Result<()> {
 	// read superblock, BlockGroupDescriptor, some usefully data
-	let ext2_fs = ext2::Ext2FS::new(_device.try_clone()?)?;
+	let ext2_fs = ext2::Ext2FS::new(_device)?;
 
 	ext2_fs.create_debug_os_info()?;
-	// iterate over unused blocks
-	let block_iter = ext2::BlockIter::new(&ext2_fs);
-
-	let block_size = ext2_fs.super_block.block_size();
-	// Buffer to store current JPEG data
-	let mut current_jpeg: Option<Vec<u8>> = None;
-	// Start block of JPEG
-	let mut jpeg_start_block: Option<usize> = None;
-
-	// Use the iterator to process blocks
-	for (group_number, block_number, is_used) in block_iter {
-		if is_used {
-			continue; // Skip used blocks
+
+	// iterate over unused blocks and carve every known file format
+	let mut engine = carve::CarvingEngine::new();
+	engine.add(Box::new(carve::JpegCarver));
+	engine.add(Box::new(carve::PngCarver));
+	engine.add(Box::new(carve::GifCarver));
+	engine.add(Box::new(carve::BmpCarver));
+	engine.add(Box::new(carve::PdfCarver));
+	engine.add(Box::new(carve::ZipCarver));
+	engine.add(Box::new(carve::Mp4Carver));
+	engine.add(Box::new(carve::SqliteCarver));
+	engine.add(Box::new(carve::RiffCarver));
+	engine.add(Box::new(carve::FlacCarver));
+	engine.add(Box::new(carve::Mp3Carver));
+	engine.add(Box::new(carve::GzipCarver));
+	engine.add(Box::new(carve::Bzip2Carver));
+	engine.add(Box::new(carve::XzCarver));
+	engine.add(Box::new(carve::SevenZipCarver));
+	engine.add(Box::new(carve::TarCarver));
+	engine.add(Box::new(carve::ElfCarver));
+	engine.add(Box::new(carve::ScriptCarver::new(ext2_fs.super_block.block_size() as usize)));
+	// Single database pages are small and plentiful, only search them on request
+	if sqlite_pages {
+		engine.add(Box::new(carve::SqlitePageCarver));
+	}
+	// Text has no signature, it's only what none of the formats above is
+	engine.add(Box::new(carve::TextCarver::new(ext2_fs.super_block.block_size() as usize)));
+
+	let recovered = engine.run(&ext2_fs, _path)?;
+	println!("Recovered {} files", recovered);
+
+	Ok(())
+}
+
+// Name for a restored file: the original name, if it survived, without control characters
+// and cut to the 255 bytes a file name can have
+fn undeleted_file_name(inode_number: u32, name: Option<&String>) -> String {
+	let mut filename = format!("undeleted_{}", inode_number);
+	if let Some(name) = name {
+		filename.push('_');
+		for c in name.chars().map(|c| if c.is_control() || c == '/' { '_' } else { c }) {
+			if filename.len() + c.len_utf8() > 255 {
+				break;
+			}
+			filename.push(c);
 		}
+	}
+	filename
+}
 
-		// Calculate the block offset in the FS
-		let block_offset = (block_number as u64) * (block_size as u64);
-
-		// Read the block data
-		_device.seek(io::SeekFrom::Start(block_offset))?;
-		let mut block_data = vec![0; block_size as usize];
-		_device.read_exact(&mut block_data)?;
-
-		// Search for JPEG SOI (FFD8) and EOI (FFD9) markers
-		// For test, how it works:
-		// let block_data = [0x12, 0x34, 0xFF, 0xD8, 0x99];
-		//     if let Some(pos) = block_data.windows(2).position(|w| w == [0xFF, 0xD8]) {
-		//         println!("JPEG SOI marker found at index: {}", pos);
-		//     }
-		if let Some(pos) = block_data.windows(2).position(|w| w == [0xFF, 0xD8]) {
-			// Found JPEG Start
-			jpeg_start_block = Some(block_number);
-			// Start saving JPEG data to Vector
-			current_jpeg = Some(block_data[pos..].to_vec());
-			println!("JPEG Start found in Block Group {}, Block {}", group_number, block_number);
-		} else if let Some(pos) = block_data.windows(2).position(|w| w == [0xFF, 0xD9]) {
-			// Found JPEG End
-			println!("JPEG End found in Block Group {}, Block {}", group_number, block_number);
-			// Check if we hat some data before
-			if let Some(ref mut jpeg_data) = current_jpeg {
-				// Add the block up to EOI
-				// create slice of block_data from the beginning of the block up to and including the pos + 1 index.
-				jpeg_data.extend_from_slice(&block_data[..=pos + 1]);
-
-				// Save the JPEG data to a file.
-				let filename = format!("{}/recovered_{}_{}.jpg", _path, group_number, jpeg_start_block.unwrap());
-				let mut file = fs::File::create(&filename)?;
-				file.write_all(jpeg_data)?;
-
-				println!("JPEG saved to {}", filename);
-
-				// Reset current JPEG buffer
-				current_jpeg = None;
-				jpeg_start_block = None;
+// Writes the data of a deleted inode to filename
+fn save_inode(ext2_fs: &ext2::Ext2FS, inode_number: u32, inode: &ext2::Inode, filename: &str) -> io::Result<()> {
+	let mut file = fs::File::create(filename)?;
+	io::copy(&mut ext2_fs.read_inode_data(inode_number)?, &mut file)?;
+	// Restore the original timestamps
+	file.set_times(fs::FileTimes::new().set_accessed(inode.accessed()).set_modified(inode.modified()))
+}
+
+fn undelete_files(_device: fs::File, _path: &str) -> io::Result<()> {
+	// read superblock, BlockGroupDescriptor, inode table
+	let ext2_fs = ext2::Ext2FS::new(_device)?;
+
+	ext2_fs.create_debug_os_info()?;
+	// Original file names from the directories, if they survived
+	let names = ext2_fs.deleted_file_names();
+
+	for (_, inode_number, is_allocated, inode) in ext2::InodeIter::new(&ext2_fs) {
+		// ext2 only sets i_dtime and clears the bitmaps, the block pointers are still there
+		let inode = match inode {
+			Some(inode) if !is_allocated && inode.is_deleted() && inode.is_regular_file() => inode,
+			_ => continue,
+		};
+
+		let blocks = match ext2_fs.inode_blocks(inode) {
+			Ok(blocks) => blocks,
+			Err(e) => {
+				println!("Deleted inode {}: {}", inode_number, e);
+				continue;
 			}
-		} else if let Some(ref mut jpeg_data) = current_jpeg {
-			// Continue buffering JPEG data
-			jpeg_data.extend_from_slice(&block_data);
+		};
+		if blocks.is_empty() {
+			println!("Deleted inode {} has no data blocks left", inode_number);
+			continue;
 		}
+		// If a block is allocated again, it belongs to another file by now
+		if let Some(block) = blocks.iter().find(|&&block| ext2_fs.is_block_used(block)) {
+			println!("Deleted inode {}: block {} is already reused", inode_number, block);
+			continue;
+		}
+
+		let filename = format!("{}/{}", _path, undeleted_file_name(inode_number, names.get(&inode_number)));
+		// One file that can't be written is no reason to give up on the others
+		if let Err(e) = save_inode(&ext2_fs, inode_number, inode, &filename) {
+			println!("Deleted inode {}: {}", inode_number, e);
+			continue;
+		}
+
+		println!("Deleted inode {} ({} bytes) saved to {}", inode_number, inode.size(), filename);
 	}
 
 	Ok(())
 }
 
+fn list_directory(_device: fs::File, _path: &str) -> io::Result<()> {
+	let ext2_fs = ext2::Ext2FS::new(_device)?;
+	let inode_number = ext2_fs.lookup(_path)?;
+
+	for entry in ext2_fs.read_dir(inode_number)? {
+		// Deleted or broken entries can point to invalid inodes
+		let size = ext2_fs.inode(entry.inode()).map(|inode| inode.size()).unwrap_or(0);
+		println!("{:>8} {:<16} {:>12} {}", entry.inode(), entry.file_type_name(), size, entry.name());
+	}
+
+	// Deleted entries, that are still in the directory, with the state of the inode they point to
+	for entry in ext2_fs.read_deleted_dir_entries(inode_number)? {
+		let (size, status) = ext2_fs.inode(entry.inode())
+			.map(|inode| (inode.size(), inode.status()))
+			.unwrap_or((0, "invalid"));
+		println!("{:>8} {:<16} {:>12} {} (deleted, inode {})", entry.inode(), entry.file_type_name(), size, entry.name(), status);
+	}
+	Ok(())
+}
+
 fn main() -> io::Result<()> {
 	use std::env::args;
 	// Collect command-line arguments
 	let args: Vec<String> = args().collect();
 
 	// Check if the required arguments are passed
-	if args.len() < 3 {
+	if args.len() < 3 || (args[2] == "--ls" && args.len() < 4) {
 		// Red text: "\x1b[31m ...  \x1b[0m"
-		eprintln!("\x1b[31mUsage: {} <input_file> <output_file>\x1b[0m", args[0]);
+		eprintln!("\x1b[31mUsage: {} <input_file> <output_file> [--undelete | --sqlite-pages]\x1b[0m", args[0]);
+		eprintln!("\x1b[31m       {} <input_file> --ls <directory>\x1b[0m", args[0]);
 		std::process::exit(1);
 	}
 
 	let device_path = args[1].to_string();
+
+	// Only list a directory of the live file system
+	if args[2] == "--ls" {
+		return list_directory(fs::File::open(&device_path)?, &args[3]);
+	}
+
 	let target_path = args[2].to_string();
 
+	// Either restore deleted inodes, or carve files from the unused blocks
+	let undelete = args.get(3).is_some_and(|arg| arg == "--undelete");
+	let sqlite_pages = args.get(3).is_some_and(|arg| arg == "--sqlite-pages");
+
 	fs::create_dir_all(&target_path)?;
-	match recover_files(fs::File::open(&device_path)?, &target_path){
+	let result = if undelete {
+		undelete_files(fs::File::open(&device_path)?, &target_path)
+	} else {
+		recover_files(fs::File::open(&device_path)?, &target_path, sqlite_pages)
+	};
+	match result {
 		Ok(_) => {
 			println!("Successfully recovered {}", &device_path);
 			Ok(()) // Return the correct type